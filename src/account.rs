@@ -112,7 +112,7 @@ impl<'a> AccountOrganization<'a> {
 }
 
 impl<'a> AccountTeamstream<'a> {
-    pub fn source<S>(&mut self, source: S) -> &'a mut AccountTeamstream
+    pub fn source<S>(&mut self, source: S) -> &mut AccountTeamstream<'a>
     where
         S: Into<String>,
     {
@@ -120,12 +120,12 @@ impl<'a> AccountTeamstream<'a> {
         self
     }
 
-    pub fn datetime<S>(&mut self, dt: DateTime<Utc>) -> &'a mut AccountTeamstream {
+    pub fn datetime(&mut self, dt: DateTime<Utc>) -> &mut AccountTeamstream<'a> {
         self.datetime = Some(PtDate { inner: dt });
         self
     }
 
-    pub fn typ<S>(&mut self, ty: S) -> &'a mut AccountTeamstream
+    pub fn typ<S>(&mut self, ty: S) -> &mut AccountTeamstream<'a>
     where
         S: Into<String>,
     {
@@ -133,7 +133,7 @@ impl<'a> AccountTeamstream<'a> {
         self
     }
 
-    pub fn focus<S>(&mut self, focus: S) -> &'a mut AccountTeamstream
+    pub fn focus<S>(&mut self, focus: S) -> &mut AccountTeamstream<'a>
     where
         S: Into<String>,
     {
@@ -143,7 +143,7 @@ impl<'a> AccountTeamstream<'a> {
 }
 
 impl<'a> AccountSources<'a> {
-    pub fn source<S>(&mut self, source: S) -> &'a mut AccountSources
+    pub fn source<S>(&mut self, source: S) -> &mut AccountSources<'a>
     where
         S: Into<String>,
     {
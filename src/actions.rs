@@ -1,6 +1,7 @@
 use serde_json::Value;
 
-use {PassiveTotal, Result};
+use query::{HOST_KINDS, IP_KINDS};
+use {PassiveTotal, Query, QueryKind, Result};
 
 const URL_CLASSIFICATION: &str = "/actions/classification";
 const URL_COMPROMISED: &str = "/actions/ever-compromised";
@@ -14,76 +15,76 @@ pub struct ActionsRequest<'a> {
 }
 
 request_struct!(ActionsClassification {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(ActionsCompromised {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(ActionsDynamicDns {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(ActionsMonitor {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(ActionsSinkhole {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(ActionsTags {
-    query: &'a str
+    query: Query,
 });
 
 impl<'a> ActionsRequest<'a> {
-    pub fn classification(self, query: &'a str) -> ActionsClassification<'a> {
-        ActionsClassification {
+    pub fn classification(self, query: &str) -> Result<ActionsClassification<'a>> {
+        Ok(ActionsClassification {
             pt: self.pt,
             url: URL_CLASSIFICATION,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 
-    pub fn compromised(self, query: &'a str) -> ActionsCompromised<'a> {
-        ActionsCompromised {
+    pub fn compromised(self, query: &str) -> Result<ActionsCompromised<'a>> {
+        Ok(ActionsCompromised {
             pt: self.pt,
             url: URL_COMPROMISED,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 
-    pub fn dynamic_dns(self, query: &'a str) -> ActionsDynamicDns<'a> {
-        ActionsDynamicDns {
+    pub fn dynamic_dns(self, query: &str) -> Result<ActionsDynamicDns<'a>> {
+        Ok(ActionsDynamicDns {
             pt: self.pt,
             url: URL_DDNS,
-            query,
-        }
+            query: Query::parse_as(query, QueryKind::Domain)?,
+        })
     }
 
-    pub fn monitor(self, query: &'a str) -> ActionsMonitor<'a> {
-        ActionsMonitor {
+    pub fn monitor(self, query: &str) -> Result<ActionsMonitor<'a>> {
+        Ok(ActionsMonitor {
             pt: self.pt,
             url: URL_MONITOR,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 
-    pub fn sinkhole(self, query: &'a str) -> ActionsSinkhole<'a> {
-        ActionsSinkhole {
+    pub fn sinkhole(self, query: &str) -> Result<ActionsSinkhole<'a>> {
+        Ok(ActionsSinkhole {
             pt: self.pt,
             url: URL_SINKHOLE,
-            query,
-        }
+            query: Query::parse_any(query, IP_KINDS)?,
+        })
     }
 
-    pub fn tags(self, query: &'a str) -> ActionsTags<'a> {
-        ActionsTags {
+    pub fn tags(self, query: &str) -> Result<ActionsTags<'a>> {
+        Ok(ActionsTags {
             pt: self.pt,
             url: URL_TAGS,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 }
 
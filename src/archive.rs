@@ -0,0 +1,328 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use dirs;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+use serde_json::{self, Value};
+
+use diskcache::write_atomic;
+use query::HOST_KINDS;
+use {utils, PassiveTotalError, Query, Result};
+
+const ARCHIVE_DIR: &str = "passivetotal";
+const ARCHIVE_FILE: &str = "pdns-archive.json";
+// How long to wait for another process to finish saving, and how old a lock
+// file has to be before it is assumed to be left over from a crash
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_STALE: Duration = Duration::from_secs(60);
+
+/// A passive DNS resolution kept in a `PdnsArchive`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdnsRecord {
+    /// The domain or IP address that was looked up
+    pub query: String,
+    /// What the query resolved to
+    pub resolve: String,
+    /// The DNS record type, such as `A` or `CNAME`
+    pub record_type: String,
+    /// Every source that reported the resolution
+    pub sources: BTreeSet<String>,
+    /// The earliest time the resolution was seen
+    #[serde(serialize_with = "serialize_date", deserialize_with = "deserialize_date")]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// The latest time the resolution was seen
+    #[serde(serialize_with = "serialize_date", deserialize_with = "deserialize_date")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// A local archive of every passive DNS resolution seen, so that history is
+/// kept after it falls out of the API's lookback window.
+///
+/// Resolutions are identified by their query, resolve and record type. When
+/// the same resolution is added again its sources are merged and its first
+/// and last seen times are extended to cover both observations.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{PassiveTotal, PdnsArchive};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let mut archive = PdnsArchive::open(PdnsArchive::default_path().unwrap()).unwrap();
+///
+/// let resp = pt.passive_dns("passivetotal.org").unwrap().send().unwrap();
+/// archive.add_response("passivetotal.org", &resp);
+/// archive.save().unwrap();
+///
+/// for record in archive.lookup("passivetotal.org") {
+///     println!("{} {} {:?}", record.resolve, record.record_type, record.last_seen);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct PdnsArchive {
+    path: Option<PathBuf>,
+    records: BTreeMap<(String, String, String), PdnsRecord>,
+}
+
+fn serialize_date<S>(
+    dt: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> ::std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match *dt {
+        Some(ref dt) => serializer.serialize_str(&utils::format_date(dt)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_date<'de, D>(
+    deserializer: D,
+) -> ::std::result::Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    match s {
+        Some(s) => match utils::parse_date(&s) {
+            Some(dt) => Ok(Some(dt)),
+            None => Err(D::Error::custom(format!("invalid date: {}", s))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn archive_error(path: &Path, e: &fmt::Display) -> PassiveTotalError {
+    PassiveTotalError::ArchiveError(format!("{}: {}", path.display(), e))
+}
+
+// A lock file held while the archive is saved, so that two processes saving
+// at once do not lose each other's resolutions. Removed when dropped.
+struct SaveLock {
+    path: PathBuf,
+}
+
+impl SaveLock {
+    fn acquire(archive: &Path) -> io::Result<SaveLock> {
+        let path = archive.with_extension("lock");
+        let start = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(SaveLock { path }),
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {},
+                Err(e) => return Err(e),
+            }
+
+            let age = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.map_or(false, |age| age > LOCK_STALE) {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if start.elapsed().map_or(false, |waited| waited > LOCK_TIMEOUT) {
+                return Err(io::Error::new(
+                    ErrorKind::WouldBlock,
+                    format!("timed out waiting for {}", path.display()),
+                ));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for SaveLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Keep the earliest or latest of two optional dates
+fn extend<F>(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>, pick: F) -> Option<DateTime<Utc>>
+where
+    F: Fn(DateTime<Utc>, DateTime<Utc>) -> DateTime<Utc>,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => Some(pick(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl PdnsRecord {
+    // Build a record from one of the `results` of a passive DNS response
+    fn from_result(query: &str, result: &Value) -> Option<PdnsRecord> {
+        let resolve = result.get("resolve")?.as_str()?;
+        let date = |key| result.get(key).and_then(Value::as_str).and_then(utils::parse_date);
+        let sources = match result.get("source") {
+            Some(Value::Array(sources)) => sources
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            Some(Value::String(source)) => iter::once(source.clone()).collect(),
+            _ => BTreeSet::new(),
+        };
+
+        Some(PdnsRecord {
+            query: query.to_lowercase(),
+            resolve: resolve.to_lowercase(),
+            record_type: result
+                .get("recordType")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_uppercase(),
+            sources,
+            first_seen: date("firstSeen"),
+            last_seen: date("lastSeen"),
+        })
+    }
+
+    fn key(&self) -> (String, String, String) {
+        (self.query.clone(), self.resolve.clone(), self.record_type.clone())
+    }
+
+    // Merge another observation of the same resolution into this one.
+    // Returns `true` if anything changed.
+    fn merge(&mut self, other: PdnsRecord) -> bool {
+        let before = self.clone();
+        self.sources.extend(other.sources);
+        self.first_seen = extend(self.first_seen, other.first_seen, cmp::min);
+        self.last_seen = extend(self.last_seen, other.last_seen, cmp::max);
+        *self != before
+    }
+}
+
+impl PdnsArchive {
+    /// Create an empty archive that is only kept in memory
+    pub fn new() -> PdnsArchive {
+        PdnsArchive::default()
+    }
+
+    /// The default archive file, `passivetotal/pdns-archive.json` under the
+    /// user's data directory, such as `~/.local/share` on Linux
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(ARCHIVE_DIR).join(ARCHIVE_FILE))
+    }
+
+    /// Open the archive stored at `path`. The file is created when the
+    /// archive is first saved.
+    pub fn open<P>(path: P) -> Result<PdnsArchive>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let records: Vec<PdnsRecord> = match File::open(&path) {
+            Ok(f) => serde_json::from_reader(BufReader::new(f))
+                .map_err(|e| archive_error(&path, &e))?,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(archive_error(&path, &e)),
+        };
+
+        let mut archive = PdnsArchive {
+            path: Some(path),
+            records: BTreeMap::new(),
+        };
+        for record in records {
+            archive.insert(record);
+        }
+        Ok(archive)
+    }
+
+    /// Add a resolution, merging it with any existing observation of the
+    /// same resolution. Returns `true` if the archive changed.
+    pub fn insert(&mut self, record: PdnsRecord) -> bool {
+        let key = record.key();
+        if let Some(existing) = self.records.get_mut(&key) {
+            return existing.merge(record);
+        }
+        self.records.insert(key, record);
+        true
+    }
+
+    /// Add every resolution in a `passive_dns` response for `query`.
+    /// Responses without a `results` array are ignored.
+    /// Returns the number of resolutions that were new or changed.
+    pub fn add_response(&mut self, query: &str, resp: &Value) -> usize {
+        let results = match resp.get("results") {
+            Some(Value::Array(results)) => results,
+            _ => return 0,
+        };
+
+        // Store the query the same way it was sent, so a domain looked up
+        // as a URL or a defanged domain is archived under the domain
+        let query = Query::parse_any(query, HOST_KINDS)
+            .map(|q| q.to_string())
+            .unwrap_or_else(|_| query.to_owned());
+        results
+            .iter()
+            .filter_map(|r| PdnsRecord::from_result(&query, r))
+            .filter(|r| self.insert(r.clone()))
+            .count()
+    }
+
+    /// Returns the resolutions where `value` is either the query or the
+    /// resolve, ordered by query and resolve
+    pub fn lookup(&self, value: &str) -> Vec<&PdnsRecord> {
+        let value = value.to_lowercase();
+        self.records
+            .values()
+            .filter(|r| r.query == value || r.resolve == value)
+            .collect()
+    }
+
+    /// Returns every resolution in the archive, ordered by query and resolve
+    pub fn records(&self) -> Vec<&PdnsRecord> {
+        self.records.values().collect()
+    }
+
+    /// The number of resolutions in the archive
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Check if the archive has no resolutions
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Save the archive to the file it was opened from.
+    ///
+    /// The file is read again first and merged with this archive, so
+    /// resolutions saved by other processes since it was opened are kept.
+    /// A lock file next to the archive is held while saving so that other
+    /// processes wait for the save to finish, and the new file is written
+    /// alongside the old one before replacing it. Archives created with
+    /// `new` are not saved.
+    pub fn save(&mut self) -> Result<()> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| archive_error(dir, &e))?;
+        }
+        let _lock = SaveLock::acquire(&path).map_err(|e| archive_error(&path, &e))?;
+
+        for (_, record) in PdnsArchive::open(path.clone())?.records {
+            self.insert(record);
+        }
+
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        write_atomic(&tmp, &path, &self.records()).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            archive_error(&path, &e)
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use serde_json::Value;
+
+use {PassiveTotal, Result};
+
+const URL_ARTICLES: &str = "/articles";
+
+pub struct ArticlesRequest<'a> {
+    pt: &'a PassiveTotal,
+}
+
+request_struct!(ArticlesList {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+});
+
+impl<'a> ArticlesRequest<'a> {
+    pub fn list(self) -> ArticlesList<'a> {
+        ArticlesList {
+            pt: self.pt,
+            url: URL_ARTICLES,
+            sort: None,
+            order: None,
+            page: None,
+        }
+    }
+}
+
+impl<'a> ArticlesList<'a> {
+    pub fn sort<S>(&mut self, sort: S) -> &mut ArticlesList<'a>
+    where
+        S: Into<String>,
+    {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn order<S>(&mut self, order: S) -> &mut ArticlesList<'a>
+    where
+        S: Into<String>,
+    {
+        self.order = Some(order.into());
+        self
+    }
+}
+
+impl_send!(ArticlesList);
+
+impl_paged!(ArticlesList, "articles");
+
+impl PassiveTotal {
+    pub fn articles(&self) -> ArticlesRequest {
+        ArticlesRequest { pt: self }
+    }
+}
@@ -0,0 +1,271 @@
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::vec;
+
+use serde_json::Value;
+
+use Result;
+
+const DEFAULT_WORKERS: usize = 4;
+
+/// A request that can be sent to the Passivetotal API.
+///
+/// Implemented by every request builder, such as the one returned by
+/// `PassiveTotal::passive_dns`, so that different requests can be run
+/// together in a `Batch`.
+pub trait Request: Sync {
+    /// Send the request, see the `send` method of each request builder.
+    fn send(&self) -> Result<Value>;
+}
+
+/// Runs many requests at once on a pool of worker threads.
+///
+/// Requests still go through their `PassiveTotal` client, so any rate limit
+/// set with `PassiveTotal::with_rate_limit` applies across all workers. A
+/// failed request does not stop the rest of the batch, its error is
+/// returned alongside its key instead. Requests created with `Batch::new`
+/// are keyed by their index, those created with `Batch::keyed` by any
+/// value given with them, such as the query.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{Batch, PassiveTotal};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey").with_rate_limit(60);
+/// let requests = ["example.com", "example.org"]
+///     .iter()
+///     .map(|q| (*q, pt.passive_dns(q).unwrap()));
+///
+/// for (query, resp) in Batch::keyed(requests).workers(8) {
+///     println!("{}: {:?}", query, resp);
+/// }
+/// ```
+pub struct Batch<R, K = usize> {
+    keys: Vec<K>,
+    requests: Vec<R>,
+    workers: usize,
+    in_order: bool,
+}
+
+impl<R> Batch<R>
+where
+    R: Request,
+{
+    /// Create a new batch of requests using 4 workers, returning results
+    /// in the same order as the requests.
+    pub fn new<I>(requests: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+    {
+        let requests: Vec<R> = requests.into_iter().collect();
+        Batch::keyed((0..requests.len()).zip(requests))
+    }
+}
+
+impl<R, K> Batch<R, K>
+where
+    R: Request,
+{
+    /// Create a new batch from pairs of a key and a request. Each result
+    /// is returned with the key of its request.
+    pub fn keyed<I>(requests: I) -> Self
+    where
+        I: IntoIterator<Item = (K, R)>,
+    {
+        let (keys, requests) = requests.into_iter().unzip();
+        Batch {
+            keys,
+            requests,
+            workers: DEFAULT_WORKERS,
+            in_order: true,
+        }
+    }
+
+    /// Set the number of requests to run at once, at least 1
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Return results in the same order as the requests if `true`, the
+    /// default, or as soon as each request completes if `false`
+    pub fn in_order(mut self, in_order: bool) -> Self {
+        self.in_order = in_order;
+        self
+    }
+
+    /// The number of requests in the batch
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Check if the batch has no requests
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Run the batch, calling `f` with the key of each request and its
+    /// result as they become available.
+    ///
+    /// Returns once every request has completed.
+    pub fn for_each<F>(self, mut f: F)
+    where
+        F: FnMut(K, Result<Value>),
+    {
+        let mut keys: Vec<Option<K>> = self.keys.into_iter().map(Some).collect();
+        run(&self.requests, self.workers, self.in_order, |i, result| {
+            if let Some(key) = keys[i].take() {
+                f(key, result);
+            }
+        });
+    }
+
+    /// Run the batch and collect the key and result of each request.
+    pub fn collect(self) -> Vec<(K, Result<Value>)> {
+        let mut results = Vec::with_capacity(self.len());
+        self.for_each(|key, result| results.push((key, result)));
+        results
+    }
+}
+
+impl<R, K> IntoIterator for Batch<R, K>
+where
+    R: Request,
+{
+    type Item = (K, Result<Value>);
+    type IntoIter = BatchIter<R, K>;
+
+    fn into_iter(self) -> BatchIter<R, K> {
+        BatchIter {
+            keys: self.keys.into_iter(),
+            requests: self.requests.into_iter(),
+            workers: self.workers,
+            in_order: self.in_order,
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+/// An iterator over the key and result of each request in a `Batch`.
+///
+/// Requests are only sent as results are needed, one round of as many
+/// requests as there are workers at a time. Use `Batch::for_each` to keep
+/// every worker busy until the whole batch has completed.
+pub struct BatchIter<R, K = usize> {
+    keys: vec::IntoIter<K>,
+    requests: vec::IntoIter<R>,
+    workers: usize,
+    in_order: bool,
+    ready: VecDeque<(K, Result<Value>)>,
+}
+
+impl<R, K> Iterator for BatchIter<R, K>
+where
+    R: Request,
+{
+    type Item = (K, Result<Value>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() {
+            let requests: Vec<R> = self.requests.by_ref().take(self.workers).collect();
+            let mut keys: Vec<Option<K>> =
+                self.keys.by_ref().take(requests.len()).map(Some).collect();
+            let ready = &mut self.ready;
+            run(&requests, self.workers, self.in_order, |i, result| {
+                if let Some(key) = keys[i].take() {
+                    ready.push_back((key, result));
+                }
+            });
+        }
+
+        self.ready.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ready.len() + self.requests.len();
+        (len, Some(len))
+    }
+}
+
+// Worker threads that are joined when dropped, so they never outlive the
+// requests they borrow even if the caller of `run` panics
+struct Workers(Vec<thread::JoinHandle<()>>);
+
+impl Workers {
+    // Start a thread running `work`. The thread may borrow data for `'a`,
+    // so it must be joined by dropping `self` before `'a` ends.
+    fn spawn<'a>(&mut self, work: Box<FnMut() + Send + 'a>) {
+        // Safe because every thread is joined before `Workers` is dropped
+        let mut work: Box<FnMut() + Send + 'static> = unsafe { mem::transmute(work) };
+        self.0.push(thread::spawn(move || (*work)()));
+    }
+
+    // Wait for every thread to finish, panicking if any of them did
+    fn join(mut self) {
+        let mut panicked = false;
+        for worker in self.0.drain(..) {
+            panicked |= worker.join().is_err();
+        }
+        if panicked {
+            panic!("a batch worker panicked");
+        }
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        for worker in self.0.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// Send `requests` on up to `workers` threads, calling `f` with the index of
+// each request and its result
+fn run<R, F>(requests: &[R], workers: usize, in_order: bool, mut f: F)
+where
+    R: Request,
+    F: FnMut(usize, Result<Value>),
+{
+    let queue: Arc<Mutex<VecDeque<(usize, &R)>>> =
+        Arc::new(Mutex::new(requests.iter().enumerate().collect()));
+    let (tx, rx) = mpsc::channel();
+    let mut threads = Workers(Vec::new());
+
+    for _ in 0..cmp::min(workers, requests.len()) {
+        let queue = queue.clone();
+        let tx = tx.clone();
+        threads.spawn(Box::new(move || loop {
+            let next = queue.lock().unwrap().pop_front();
+            match next {
+                Some((i, req)) => {
+                    if tx.send((i, req.send())).is_err() {
+                        break;
+                    }
+                },
+                None => break,
+            }
+        }));
+    }
+    drop(tx);
+
+    // Results that arrived before an earlier request completed
+    let mut pending = BTreeMap::new();
+    let mut expected = 0;
+    for (i, result) in rx {
+        if !in_order {
+            f(i, result);
+            continue;
+        }
+
+        pending.insert(i, result);
+        while let Some(result) = pending.remove(&expected) {
+            f(expected, result);
+            expected += 1;
+        }
+    }
+
+    threads.join();
+}
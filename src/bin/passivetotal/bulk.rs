@@ -0,0 +1,132 @@
+//! Bulk lookups of queries read from a file or stdin.
+use std::cmp;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use passivetotal_reqwest::refang;
+use serde_json::Value;
+
+/// The result of looking up a single query, with any error as a message
+pub type Lookup = (String, Result<Value, String>);
+
+/// Read queries from a file, or stdin if `path` is `-`.
+///
+/// See `parse_queries` for the format.
+pub fn read_queries(path: &str) -> io::Result<Vec<String>> {
+    if path == "-" {
+        parse_queries(BufReader::new(io::stdin()))
+    } else {
+        parse_queries(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Read queries one per line and refang them. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn parse_queries<R: BufRead>(reader: R) -> io::Result<Vec<String>> {
+    let mut queries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            queries.push(refang(line));
+        }
+    }
+    Ok(queries)
+}
+
+/// Run `lookup` for each query using up to `jobs` threads.
+///
+/// Results are returned in the same order as the queries.
+pub fn run_all<F>(queries: Vec<String>, jobs: usize, lookup: F) -> Vec<Lookup>
+where
+    F: Fn(&str) -> Result<Value, String> + Send + Sync + 'static,
+{
+    let queries = Arc::new(queries);
+    let lookup = Arc::new(lookup);
+    let next = Arc::new(AtomicUsize::new(0));
+    let results = Arc::new(Mutex::new(vec![None; queries.len()]));
+
+    let workers: Vec<_> = (0..cmp::max(1, cmp::min(jobs, queries.len())))
+        .map(|_| {
+            let queries = queries.clone();
+            let lookup = lookup.clone();
+            let next = next.clone();
+            let results = results.clone();
+            thread::spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= queries.len() {
+                    break;
+                }
+                let result = lookup(&queries[i]);
+                results.lock().unwrap()[i] = Some(result);
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("lookup thread panicked");
+    }
+
+    let mut results = results.lock().unwrap();
+    queries
+        .iter()
+        .zip(results.drain(..))
+        .map(|(q, r)| (q.clone(), r.expect("every query is looked up")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn parse_from_reader() {
+        let input = "# queries\n\nexample.com\n  evil[.]com  \nhxxp://bad.example.org/path\n";
+        assert_eq!(
+            parse_queries(input.as_bytes()).unwrap(),
+            vec!["example.com", "evil.com", "http://bad.example.org/path"]
+        );
+    }
+
+    #[test]
+    fn results_in_query_order() {
+        let queries: Vec<String> = (0..8).map(|i| format!("q{}.example.com", i)).collect();
+        // Later queries finish first
+        let failing = [0, 3, 6];
+        let results = run_all(queries.clone(), 4, move |q| {
+            let i: u64 = q[1..2].parse().unwrap();
+            thread::sleep(Duration::from_millis((8 - i) * 5));
+            if failing.contains(&i) {
+                Err(format!("{} failed", q))
+            } else {
+                Ok(json!(q))
+            }
+        });
+
+        assert_eq!(results.len(), queries.len());
+        for (i, result) in results.iter().enumerate() {
+            let q = &queries[i];
+            assert_eq!(&result.0, q);
+            if failing.contains(&(i as u64)) {
+                assert_eq!(result.1, Err(format!("{} failed", q)));
+            } else {
+                assert_eq!(result.1, Ok(json!(q)));
+            }
+        }
+    }
+
+    #[test]
+    fn any_number_of_jobs() {
+        let queries = vec![String::from("example.com"), String::from("example.org")];
+        for &jobs in &[0, 1, 10] {
+            let results = run_all(queries.clone(), jobs, |q| Ok(json!(q)));
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[1], (String::from("example.org"), Ok(json!("example.org"))));
+        }
+        assert!(run_all(Vec::new(), 4, |q| Ok(json!(q))).is_empty());
+    }
+}
@@ -0,0 +1,885 @@
+//! Command line app for querying the Passivetotal v2 API.
+//!
+//! Reads the Passivetotal username and API key from the file given with
+//! `--config` or from `$HOME/.passivetotal.toml`, see `Config::load`.
+//!
+//! Subcommands that take a query can instead look up every query in a file
+//! given with `--input`.
+//!
+//! Responses are cached on disk in `DiskCache::default_dir()` so repeated
+//! lookups do not use up the API quota, see `--no-cache` and `--refresh`.
+//! With `--offline` every response comes from the cache.
+//!
+//! Passive DNS results are added to the local `PdnsArchive`, which can be
+//! searched with the `archive` subcommand. `whois diff` stores WHOIS records
+//! in a `WhoisStore` and shows what changed since the last one. `watch`
+//! keeps checking a watchlist and reports changes as JSON events.
+extern crate chrono;
+extern crate clap;
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use passivetotal_reqwest::{
+    refang, Config, DiskCache, EventSink, Expansion, Graph, Paged, PassiveTotal,
+    PassiveTotalError, PdnsArchive, Pivot, Pivoter, Query, SslField, TeamstreamFollower,
+    WatchEvent, Watcher, WebhookSink, WhoisField, WhoisStore, WriterSink,
+};
+use serde_json::Value;
+
+use output::{Format, Projection};
+
+mod bulk;
+mod output;
+
+type CliResult<T> = Result<T, Box<Error>>;
+
+fn query_arg() -> Arg<'static, 'static> {
+    Arg::with_name("QUERY")
+        .required_unless("input")
+        .conflicts_with("input")
+        .help("Query to look up")
+}
+
+fn input_arg() -> Arg<'static, 'static> {
+    Arg::with_name("input")
+        .long("input")
+        .short("i")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Look up each query in FILE, or stdin if FILE is -, one per line")
+}
+
+fn field_arg() -> Arg<'static, 'static> {
+    Arg::with_name("FIELD").required(true).help("Field to search")
+}
+
+fn query_command(name: &'static str, about: &'static str) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(query_arg())
+        .arg(input_arg())
+}
+
+// Arguments to fetch every page of a paged endpoint
+fn paged_command(command: App<'static, 'static>) -> App<'static, 'static> {
+    command
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .help("Fetch every page of results"),
+        )
+        .arg(
+            Arg::with_name("max-pages")
+                .long("max-pages")
+                .takes_value(true)
+                .help("Fetch at most this many pages of results, implies --all"),
+        )
+        .arg(
+            Arg::with_name("max-items")
+                .long("max-items")
+                .takes_value(true)
+                .help("Fetch at most this many results, implies --all"),
+        )
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("passivetotal")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Query the Passivetotal v2 API")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .short("c")
+                .takes_value(true)
+                .global(true)
+                .help("Configuration file, defaults to $HOME/.passivetotal.toml"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .short("p")
+                .takes_value(true)
+                .global(true)
+                .env("PT_PROFILE")
+                .help(
+                    "Named profile from the configuration file to use, ignoring PT_USERNAME \
+                     and PT_APIKEY",
+                ),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .global(true)
+                .possible_values(output::FORMATS)
+                .default_value("json")
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true)
+                .global(true)
+                .default_value("4")
+                .help("Number of lookups to run at once with --input"),
+        )
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .global(true)
+                .help("Do not read or store responses in the cache"),
+        )
+        .arg(
+            Arg::with_name("refresh")
+                .long("refresh")
+                .global(true)
+                .conflicts_with("no-cache")
+                .help("Ignore cached responses but store the new ones"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("offline")
+                .global(true)
+                .conflicts_with_all(&["no-cache", "refresh"])
+                .help("Only answer from the cache, never using the network"),
+        )
+        .subcommand(query_command("pdns", "Passive DNS records for a domain or IP"))
+        .subcommand(query_command(
+            "pdns-unique",
+            "Unique passive DNS resolutions for a domain or IP",
+        ))
+        .subcommand(
+            SubCommand::with_name("whois")
+                .about("WHOIS lookups and searches")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(query_command("info", "WHOIS record for a domain"))
+                .subcommand(paged_command(
+                    SubCommand::with_name("search")
+                        .about("Search WHOIS records by field")
+                        .arg(field_arg())
+                        .arg(query_arg())
+                        .arg(input_arg()),
+                ))
+                .subcommand(paged_command(query_command(
+                    "keyword",
+                    "Search WHOIS records by keyword",
+                )))
+                .subcommand(query_command(
+                    "diff",
+                    "Changes to a WHOIS record since it was last stored",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("ssl")
+                .about("SSL certificate lookups and searches")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(query_command("cert", "SSL certificate by SHA-1"))
+                .subcommand(paged_command(query_command(
+                    "history",
+                    "SSL certificate history for a SHA-1 or IP",
+                )))
+                .subcommand(
+                    SubCommand::with_name("search")
+                        .about("Search SSL certificates by field")
+                        .arg(field_arg())
+                        .arg(query_arg())
+                        .arg(input_arg()),
+                )
+                .subcommand(query_command("keyword", "Search SSL certificates by keyword")),
+        )
+        .subcommand(
+            SubCommand::with_name("enrich")
+                .about("Enrichment data")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(query_command("data", "Enrichment data for a domain or IP"))
+                .subcommand(query_command("osint", "OSINT for a domain or IP"))
+                .subcommand(query_command("malware", "Malware for a domain or IP"))
+                .subcommand(query_command("subdomains", "Subdomains of a domain")),
+        )
+        .subcommand(
+            SubCommand::with_name("actions")
+                .about("Action flags for a query")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(query_command("classification", "Classification"))
+                .subcommand(query_command("compromised", "Ever compromised status"))
+                .subcommand(query_command("dynamic-dns", "Dynamic DNS status"))
+                .subcommand(query_command("monitor", "Monitoring status"))
+                .subcommand(query_command("sinkhole", "Sinkhole status"))
+                .subcommand(query_command("tags", "Tags")),
+        )
+        .subcommand(
+            SubCommand::with_name("account")
+                .about("Account information")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("info").about("Account details"))
+                .subcommand(SubCommand::with_name("history").about("Account history"))
+                .subcommand(SubCommand::with_name("monitors").about("Active monitors"))
+                .subcommand(SubCommand::with_name("organization").about("Organization details"))
+                .subcommand(SubCommand::with_name("quota").about("Account quota"))
+                .subcommand(
+                    SubCommand::with_name("sources")
+                        .about("Data sources")
+                        .arg(Arg::with_name("source").long("source").takes_value(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("teamstream")
+                        .about("Organization teamstream")
+                        .arg(Arg::with_name("source").long("source").takes_value(true))
+                        .arg(
+                            Arg::with_name("datetime")
+                                .long("datetime")
+                                .takes_value(true)
+                                .help("Only events after this UTC time, as YYYY-MM-DD HH:MM:SS"),
+                        )
+                        .arg(Arg::with_name("type").long("type").takes_value(true))
+                        .arg(Arg::with_name("focus").long("focus").takes_value(true))
+                        .arg(
+                            Arg::with_name("follow")
+                                .long("follow")
+                                .help("Keep printing new events as JSON lines as they happen"),
+                        )
+                        .arg(
+                            Arg::with_name("cursor")
+                                .long("cursor")
+                                .takes_value(true)
+                                .requires("follow")
+                                .help("File to keep the position in the teamstream in"),
+                        )
+                        .arg(
+                            Arg::with_name("interval")
+                                .long("interval")
+                                .takes_value(true)
+                                .default_value("60")
+                                .requires("follow")
+                                .help("Seconds to wait between checks for new events"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pivot")
+                .about("Expand from an indicator to related infrastructure")
+                .arg(Arg::with_name("QUERY").required(true).help("Indicator to start from"))
+                .arg(
+                    Arg::with_name("pivot")
+                        .long("pivot")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help(
+                            "Pivot to make, all if not given: passive-dns, whois-email, \
+                             whois-domains, ssl-certificates, ssl-ips or subdomains",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .default_value("2")
+                        .help("Maximum number of pivots from the starting indicator"),
+                )
+                .arg(
+                    Arg::with_name("fan-out")
+                        .long("fan-out")
+                        .takes_value(true)
+                        .default_value("25")
+                        .help("Maximum number of results to use from each pivot"),
+                )
+                .arg(
+                    Arg::with_name("max-nodes")
+                        .long("max-nodes")
+                        .takes_value(true)
+                        .default_value("250")
+                        .help("Stop after finding this many indicators"),
+                )
+                .arg(
+                    Arg::with_name("graph")
+                        .long("graph")
+                        .takes_value(true)
+                        .possible_values(&["graphml", "dot", "json"])
+                        .help("Print the indicators found as a graph in this format"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Watch a list of domains and IPs and report changes")
+                .arg(
+                    Arg::with_name("WATCHLIST")
+                        .required(true)
+                        .help("File of domains and IPs to watch, one per line, or - for stdin"),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("3600")
+                        .help("Seconds to wait between checks"),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .takes_value(true)
+                        .help("File to keep the watched state in between runs"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("Append events to this file instead of printing them"),
+                )
+                .arg(
+                    Arg::with_name("webhook")
+                        .long("webhook")
+                        .takes_value(true)
+                        .conflicts_with("output")
+                        .help("POST each event as JSON to this URL"),
+                )
+                .arg(
+                    Arg::with_name("once")
+                        .long("once")
+                        .help("Check the watchlist once and exit"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Search the local passive DNS archive")
+                .arg(
+                    Arg::with_name("QUERY")
+                        .help("Domain or IP to find as a query or resolve, all if not given"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .about("Inspect or purge the response cache")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("info").about("List cached responses"))
+                .subcommand(
+                    SubCommand::with_name("purge")
+                        .about("Remove cached responses")
+                        .arg(
+                            Arg::with_name("expired")
+                                .long("expired")
+                                .help("Only remove expired responses"),
+                        ),
+                ),
+        )
+}
+
+fn parse_datetime(s: &str) -> CliResult<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    let dt = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .map_err(|e| format!("Invalid datetime {}: {}", s, e))?;
+    Ok(Utc.from_utc_datetime(&dt))
+}
+
+fn max_arg(m: &ArgMatches, name: &str) -> CliResult<Option<usize>> {
+    match m.value_of(name) {
+        Some(max) => {
+            let max = max.parse().map_err(|e| format!("Invalid --{}: {}", name, e))?;
+            Ok(Some(max))
+        },
+        None => Ok(None),
+    }
+}
+
+// Send a paged request, collecting the results of every page into a single
+// response if asked to
+fn send_paged<R>(req: R, m: &ArgMatches) -> CliResult<Value>
+where
+    R: Paged,
+{
+    let max_pages = max_arg(m, "max-pages")?;
+    let max_items = max_arg(m, "max-items")?;
+    if !m.is_present("all") && max_pages.is_none() && max_items.is_none() {
+        return Ok(req.send()?);
+    }
+
+    let mut pages = req.pages();
+    if let Some(max) = max_pages {
+        pages = pages.max_pages(max);
+    }
+    if let Some(max) = max_items {
+        pages = pages.max_items(max);
+    }
+    let results = pages.collect::<Result<Vec<Value>, _>>()?;
+    Ok(json!({ "results": results }))
+}
+
+fn run_whois(pt: &PassiveTotal, m: &ArgMatches, q: &str) -> CliResult<Value> {
+    let resp = match m.subcommand() {
+        ("info", _) => pt.whois().information(q)?.send()?,
+        ("search", Some(m)) => {
+            let field: WhoisField = m.value_of("FIELD").unwrap().parse()?;
+            send_paged(pt.whois().search_field(q, field)?, m)?
+        },
+        ("keyword", Some(m)) => send_paged(pt.whois().search_keyword(q), m)?,
+        ("diff", _) => {
+            let whois = pt.whois().information(q)?.send()?;
+            let dir = WhoisStore::default_dir().ok_or("Unable to find the data directory")?;
+            let store = WhoisStore::open(dir)?;
+            let previous = store.latest(q)?.map(|s| s.fetched.to_rfc3339());
+            let changes = store.add(q, &whois)?;
+            json!({ "query": q, "previous": previous, "results": changes })
+        },
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+fn run_ssl(pt: &PassiveTotal, m: &ArgMatches, q: &str) -> CliResult<Value> {
+    let resp = match m.subcommand() {
+        ("cert", _) => pt.ssl().certificate(q)?.send()?,
+        ("history", Some(m)) => send_paged(pt.ssl().history(q)?, m)?,
+        ("search", Some(m)) => {
+            let field: SslField = m.value_of("FIELD").unwrap().parse()?;
+            pt.ssl().search_field(q, field)?.send()?
+        },
+        ("keyword", _) => pt.ssl().search_keyword(q).send()?,
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+fn run_enrich(pt: &PassiveTotal, m: &ArgMatches, q: &str) -> CliResult<Value> {
+    let resp = match m.subcommand_name() {
+        Some("data") => pt.enrichment().data(q)?.send()?,
+        Some("osint") => pt.enrichment().osint(q)?.send()?,
+        Some("malware") => pt.enrichment().malware(q)?.send()?,
+        Some("subdomains") => pt.enrichment().subdomains(q)?.send()?,
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+fn run_actions(pt: &PassiveTotal, m: &ArgMatches, q: &str) -> CliResult<Value> {
+    let resp = match m.subcommand_name() {
+        Some("classification") => pt.actions().classification(q)?.send()?,
+        Some("compromised") => pt.actions().compromised(q)?.send()?,
+        Some("dynamic-dns") => pt.actions().dynamic_dns(q)?.send()?,
+        Some("monitor") => pt.actions().monitor(q)?.send()?,
+        Some("sinkhole") => pt.actions().sinkhole(q)?.send()?,
+        Some("tags") => pt.actions().tags(q)?.send()?,
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+// Run the subcommand in `m` that looks up a query for `q`
+fn run_query(pt: &PassiveTotal, m: &ArgMatches, q: &str) -> CliResult<Value> {
+    match m.subcommand() {
+        ("pdns", _) => Ok(pt.passive_dns(q)?.send()?),
+        ("pdns-unique", _) => Ok(pt.passive_dns_unique(q)?.send()?),
+        ("whois", Some(m)) => run_whois(pt, m, q),
+        ("ssl", Some(m)) => run_ssl(pt, m, q),
+        ("enrich", Some(m)) => run_enrich(pt, m, q),
+        ("actions", Some(m)) => run_actions(pt, m, q),
+        _ => unreachable!(),
+    }
+}
+
+// Returns the matches of the innermost subcommand, which holds the QUERY
+// and --input arguments
+fn leaf<'a>(m: &'a ArgMatches<'a>) -> &'a ArgMatches<'a> {
+    match m.subcommand() {
+        (_, Some(sub)) => leaf(sub),
+        _ => m,
+    }
+}
+
+fn run_account(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<Value> {
+    let resp = match m.subcommand() {
+        ("info", _) => pt.account().info().send()?,
+        ("history", _) => pt.account().history().send()?,
+        ("monitors", _) => pt.account().monitors().send()?,
+        ("organization", _) => pt.account().organization().send()?,
+        ("quota", _) => pt.account().quota().send()?,
+        ("sources", Some(m)) => {
+            let mut req = pt.account().sources();
+            if let Some(source) = m.value_of("source") {
+                req.source(source);
+            }
+            req.send()?
+        },
+        ("teamstream", Some(m)) => {
+            let mut req = pt.account().organization().teamstream();
+            if let Some(source) = m.value_of("source") {
+                req.source(source);
+            }
+            if let Some(dt) = m.value_of("datetime") {
+                req.datetime(parse_datetime(dt)?);
+            }
+            if let Some(ty) = m.value_of("type") {
+                req.typ(ty);
+            }
+            if let Some(focus) = m.value_of("focus") {
+                req.focus(focus);
+            }
+            req.send()?
+        },
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+fn open_cache() -> CliResult<DiskCache> {
+    let dir = DiskCache::default_dir().ok_or("Unable to find the cache directory")?;
+    Ok(DiskCache::open(dir)?)
+}
+
+fn run_cache(m: &ArgMatches) -> CliResult<Value> {
+    let cache = open_cache()?;
+    let resp = match m.subcommand() {
+        ("info", _) => {
+            let entries: Vec<Value> = cache
+                .entries()?
+                .into_iter()
+                .map(|e| {
+                    json!({
+                        "account": e.account,
+                        "endpoint": e.endpoint,
+                        "params": e.params,
+                        "stored": e.stored.to_rfc3339(),
+                        "expires": e.expires.map(|dt| dt.to_rfc3339()),
+                        "size": e.size,
+                    })
+                })
+                .collect();
+            json!({ "path": cache.dir(), "results": entries })
+        },
+        ("purge", Some(m)) => json!({ "removed": cache.purge(m.is_present("expired"))? }),
+        _ => unreachable!(),
+    };
+    Ok(resp)
+}
+
+fn open_archive() -> CliResult<PdnsArchive> {
+    let path = PdnsArchive::default_path().ok_or("Unable to find the archive directory")?;
+    Ok(PdnsArchive::open(path)?)
+}
+
+// Add passive DNS responses to the archive. Failing to archive them is only
+// a warning as the lookups themselves succeeded.
+fn archive_pdns<'a, I>(responses: I)
+where
+    I: IntoIterator<Item = (&'a str, &'a Value)>,
+{
+    let archived = open_archive().and_then(|mut archive| {
+        for (q, resp) in responses {
+            archive.add_response(q, resp);
+        }
+        Ok(archive.save()?)
+    });
+    if let Err(e) = archived {
+        eprintln!("Warning: unable to archive passive DNS results: {}", e);
+    }
+}
+
+fn run_archive(m: &ArgMatches) -> CliResult<Value> {
+    let archive = open_archive()?;
+    let records = match m.value_of("QUERY") {
+        Some(q) => archive.lookup(&refang(q)),
+        None => archive.records(),
+    };
+    Ok(json!({ "results": serde_json::to_value(records)? }))
+}
+
+// Watch the queries in the watchlist, printing events as they are found
+// unless they are sent to a file or webhook
+// Warns on stderr when events can not be delivered
+struct WarningSink<S>(S);
+
+impl<S: EventSink> EventSink for WarningSink<S> {
+    fn emit(&mut self, event: &WatchEvent) -> passivetotal_reqwest::Result<()> {
+        self.0.emit(event)
+    }
+
+    fn on_error(&mut self, error: &PassiveTotalError, undelivered: usize) {
+        eprintln!(
+            "Warning: unable to deliver {} events, retrying after the next check: {}",
+            undelivered,
+            error
+        );
+    }
+}
+
+fn run_watch(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<()> {
+    let watchlist = m.value_of("WATCHLIST").unwrap();
+    let interval: u64 = m
+        .value_of("interval")
+        .unwrap()
+        .parse()
+        .map_err(|e| format!("Invalid --interval: {}", e))?;
+
+    let mut queries = Vec::new();
+    let lines = bulk::read_queries(watchlist)
+        .map_err(|e| format!("Unable to read {}: {}", watchlist, e))?;
+    for line in lines {
+        match Query::parse(&line) {
+            Ok(q) => queries.push(q),
+            Err(e) => eprintln!("Warning: skipping {}: {}", line, e),
+        }
+    }
+
+    let state = match m.value_of("state") {
+        Some(path) => Some(Path::new(path).to_path_buf()),
+        None => Watcher::default_state_file(),
+    };
+    let mut watcher = Watcher::new(pt, queries).interval(Duration::from_secs(interval));
+    if let Some(state) = state {
+        watcher = watcher.with_state_file(state)?;
+    }
+
+    let sink: Box<EventSink> = match (m.value_of("output"), m.value_of("webhook")) {
+        (Some(path), _) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Unable to open {}: {}", path, e))?;
+            Box::new(WriterSink::new(file))
+        },
+        (_, Some(url)) => Box::new(WebhookSink::new(url)),
+        _ => Box::new(WriterSink::new(io::stdout())),
+    };
+    let mut sink = WarningSink(sink);
+
+    if m.is_present("once") {
+        watcher.poll()?;
+        return Ok(watcher.deliver(&mut sink)?);
+    }
+    Ok(watcher.run(&mut sink)?)
+}
+
+// Expand from the pivot QUERY, printing a warning for each failed pivot
+fn expand(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<(Query, Expansion)> {
+    let seed = Query::parse(&refang(m.value_of("QUERY").unwrap()))?;
+    let mut pivoter = Pivoter::new(pt)
+        .max_depth(max_arg(m, "depth")?.unwrap())
+        .max_fan_out(max_arg(m, "fan-out")?.unwrap())
+        .max_nodes(max_arg(m, "max-nodes")?.unwrap());
+    if let Some(pivots) = m.values_of("pivot") {
+        let pivots = pivots.map(str::parse).collect::<Result<Vec<Pivot>, _>>()?;
+        pivoter = pivoter.pivots(pivots);
+    }
+
+    let expansion = pivoter.expand(seed.clone());
+    for e in &expansion.errors {
+        eprintln!("Warning: {} from {} failed: {}", e.pivot, e.query, e.error);
+    }
+    Ok((seed, expansion))
+}
+
+fn run_pivot(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<Value> {
+    let (seed, expansion) = expand(pt, m)?;
+    Ok(json!({
+        "query": seed,
+        "truncated": expansion.truncated,
+        "results": expansion.nodes,
+        "edges": expansion.edges,
+        "errors": expansion.errors,
+    }))
+}
+
+// Print the pivot expansion as a graph for graph tools
+fn run_graph(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<()> {
+    let (_, expansion) = expand(pt, m)?;
+    let graph = Graph::from_expansion(&expansion);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match m.value_of("graph").unwrap() {
+        "graphml" => graph.write_graphml(&mut out)?,
+        "dot" => graph.write_dot(&mut out)?,
+        _ => graph.write_json_graph(&mut out)?,
+    }
+    Ok(())
+}
+
+// Follow the teamstream, printing each new event as a JSON line
+fn run_follow(pt: &PassiveTotal, m: &ArgMatches) -> CliResult<()> {
+    let interval: u64 = m
+        .value_of("interval")
+        .unwrap()
+        .parse()
+        .map_err(|e| format!("Invalid --interval: {}", e))?;
+
+    let mut follower = TeamstreamFollower::new(pt).interval(Duration::from_secs(interval));
+    if let Some(source) = m.value_of("source") {
+        follower = follower.source(source);
+    }
+    if let Some(ty) = m.value_of("type") {
+        follower = follower.typ(ty);
+    }
+    if let Some(focus) = m.value_of("focus") {
+        follower = follower.focus(focus);
+    }
+    let cursor = match m.value_of("cursor") {
+        Some(path) => Some(Path::new(path).to_path_buf()),
+        None => TeamstreamFollower::default_cursor_file(),
+    };
+    if let Some(cursor) = cursor {
+        follower = follower.with_cursor_file(cursor)?;
+    }
+    if let Some(dt) = m.value_of("datetime") {
+        follower = follower.since(parse_datetime(dt)?);
+    }
+
+    let stdout = io::stdout();
+    for event in follower {
+        match event {
+            Ok(event) => {
+                let mut out = stdout.lock();
+                serde_json::to_writer(&mut out, &event)?;
+                writeln!(out)?;
+                out.flush()?;
+            },
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// Columns to show for a subcommand in the csv and table formats
+fn projection(m: &ArgMatches) -> Projection {
+    match m.subcommand() {
+        ("pdns", _) => Projection::PassiveDns,
+        ("pdns-unique", _) => Projection::PassiveDnsUnique,
+        ("whois", Some(m)) if m.subcommand_name() == Some("diff") => Projection::WhoisDiff,
+        ("whois", _) => Projection::Whois,
+        ("ssl", Some(m)) if m.subcommand_name() == Some("history") => Projection::SslHistory,
+        ("archive", _) => Projection::PdnsArchive,
+        _ => Projection::Generic,
+    }
+}
+
+// Look up every query in the --input file and print the results tagged
+// with their query, followed by a summary of any failures
+fn run_bulk(
+    pt: PassiveTotal,
+    m: &ArgMatches<'static>,
+    input: &str,
+    format: Format,
+) -> CliResult<()> {
+    let jobs: usize = m
+        .value_of("jobs")
+        .unwrap()
+        .parse()
+        .map_err(|e| format!("Invalid --jobs: {}", e))?;
+    let queries =
+        bulk::read_queries(input).map_err(|e| format!("Unable to read {}: {}", input, e))?;
+    let total = queries.len();
+
+    // Lookups run on their own threads, so they get their own arguments
+    let args = m.clone();
+    let results = bulk::run_all(queries, jobs, move |q| {
+        run_query(&pt, &args, q).map_err(|e| e.to_string())
+    });
+
+    if m.subcommand_name() == Some("pdns") {
+        archive_pdns(
+            results
+                .iter()
+                .filter_map(|&(ref q, ref r)| r.as_ref().ok().map(|v| (q.as_str(), v))),
+        );
+    }
+
+    let stdout = io::stdout();
+    output::write_tagged(&mut stdout.lock(), format, projection(m), &results)?;
+
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|&(ref q, ref r)| r.as_ref().err().map(|e| (q, e)))
+        .collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    for &(q, e) in &failed {
+        eprintln!("{}: {}", q, e);
+    }
+    Err(format!("{} of {} lookups failed", failed.len(), total).into())
+}
+
+fn run(m: &ArgMatches<'static>) -> CliResult<()> {
+    let format: Format = m.value_of("format").unwrap().parse()?;
+    // Subcommands that only use local files and need no configuration
+    let local = match m.subcommand() {
+        ("cache", Some(m)) => Some(run_cache(m)?),
+        ("archive", Some(m)) => Some(run_archive(m)?),
+        _ => None,
+    };
+    if let Some(resp) = local {
+        let stdout = io::stdout();
+        output::write(&mut stdout.lock(), format, projection(m), &resp)?;
+        return Ok(());
+    }
+
+    let offline = m.is_present("offline");
+    // Cached responses belong to an account, so the configuration is needed
+    // even offline
+    let config = Config::load_profile(m.value_of("config").map(Path::new), m.value_of("profile"))?;
+    let mut pt = PassiveTotal::from_config(&config);
+    // WHOIS diffs, watches and followed teamstreams compare against live
+    // results, not cached ones
+    let follow = leaf(m).is_present("follow");
+    let refresh = m.is_present("refresh") ||
+        projection(m) == Projection::WhoisDiff ||
+        m.subcommand_name() == Some("watch") ||
+        follow;
+    if !m.is_present("no-cache") {
+        match open_cache() {
+            Ok(cache) => pt = pt.with_disk_cache(cache.refresh(refresh)),
+            Err(e) => eprintln!("Warning: not caching responses: {}", e),
+        }
+        pt = pt.with_offline(offline);
+    }
+
+    let resp = match m.subcommand() {
+        ("watch", Some(m)) => return run_watch(&pt, m),
+        ("account", Some(m)) if follow => return run_follow(&pt, leaf(m)),
+        ("account", Some(m)) => run_account(&pt, m)?,
+        ("pivot", Some(m)) if m.is_present("graph") => return run_graph(&pt, m),
+        ("pivot", Some(m)) => run_pivot(&pt, m)?,
+        _ => {
+            let args = leaf(m);
+            if let Some(input) = args.value_of("input") {
+                return run_bulk(pt, m, input, format);
+            }
+            let q = args.value_of("QUERY").unwrap();
+            let resp = run_query(&pt, m, q)?;
+            if m.subcommand_name() == Some("pdns") {
+                archive_pdns(Some((q, &resp)));
+            }
+            resp
+        },
+    };
+
+    let stdout = io::stdout();
+    output::write(&mut stdout.lock(), format, projection(m), &resp)?;
+    Ok(())
+}
+
+fn main() {
+    let matches = app().get_matches();
+
+    if let Err(e) = run(&matches) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,412 @@
+//! Output formats for API responses.
+use std::cmp;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json::{self, Map, Value};
+
+use bulk::Lookup;
+
+pub const FORMATS: &[&str] = &["json", "ndjson", "csv", "table"];
+
+/// Format used to print responses
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// The full response as pretty printed JSON
+    Json,
+    /// One compact JSON object per line, one line per result
+    Ndjson,
+    /// The projected columns as CSV with a header row
+    Csv,
+    /// The projected columns as an aligned text table
+    Table,
+}
+
+/// Columns to project from a response for the `Csv` and `Table` formats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    PassiveDns,
+    PassiveDnsUnique,
+    Whois,
+    SslHistory,
+    PdnsArchive,
+    WhoisDiff,
+    /// All scalar fields of each result
+    Generic,
+}
+
+/// Rows of string cells under a header
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "table" => Ok(Format::Table),
+            _ => Err(format!("Unknown format {}", s)),
+        }
+    }
+}
+
+// Render a JSON value as a single cell. Arrays of scalars are joined with
+// `;` and other nested values are rendered as compact JSON.
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(&Value::Null) => String::new(),
+        Some(&Value::String(ref s)) => s.clone(),
+        Some(&Value::Array(ref items)) if items.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            items.iter().map(|v| cell(Some(v))).collect::<Vec<_>>().join(";")
+        },
+        Some(v) => v.to_string(),
+    }
+}
+
+// Look up a `/` separated path of object keys in `value`
+fn path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = value;
+    for key in path.split('/') {
+        value = match value.get(key) {
+            Some(v) => v,
+            None => return None,
+        };
+    }
+    Some(value)
+}
+
+/// The individual results in a response: the items of its `results` array
+/// if it has one, otherwise the response itself
+pub fn results(value: &Value) -> Vec<&Value> {
+    match value.get("results") {
+        Some(&Value::Array(ref items)) => items.iter().collect(),
+        _ => vec![value],
+    }
+}
+
+impl Projection {
+    // Columns for the fixed projections, as (header, path) pairs
+    fn columns(&self) -> &'static [(&'static str, &'static str)] {
+        match *self {
+            Projection::PassiveDns => &[
+                ("resolve", "resolve"),
+                ("recordType", "recordType"),
+                ("firstSeen", "firstSeen"),
+                ("lastSeen", "lastSeen"),
+                ("source", "source"),
+            ],
+            Projection::Whois => &[
+                ("domain", "domain"),
+                ("registrar", "registrar"),
+                ("registered", "registered"),
+                ("updated", "registryUpdatedAt"),
+                ("expires", "expiresAt"),
+                ("registrantEmail", "registrant/email"),
+                ("nameServers", "nameServers"),
+            ],
+            Projection::SslHistory => &[
+                ("sha1", "sha1"),
+                ("firstSeen", "firstSeen"),
+                ("lastSeen", "lastSeen"),
+                ("ipAddresses", "ipAddresses"),
+            ],
+            Projection::PdnsArchive => &[
+                ("query", "query"),
+                ("resolve", "resolve"),
+                ("recordType", "recordType"),
+                ("firstSeen", "firstSeen"),
+                ("lastSeen", "lastSeen"),
+                ("sources", "sources"),
+            ],
+            Projection::WhoisDiff => &[
+                ("field", "field"),
+                ("change", "change"),
+                ("old", "old"),
+                ("new", "new"),
+            ],
+            Projection::PassiveDnsUnique | Projection::Generic => &[],
+        }
+    }
+
+    /// Project a response into a table
+    pub fn table(&self, value: &Value) -> Table {
+        match *self {
+            Projection::PassiveDnsUnique => unique_table(value),
+            Projection::Generic => generic_table(&results(value)),
+            _ => {
+                let columns = self.columns();
+                Table {
+                    headers: columns.iter().map(|&(h, _)| h.to_owned()).collect(),
+                    rows: results(value)
+                        .into_iter()
+                        .map(|r| columns.iter().map(|&(_, p)| cell(path(r, p))).collect())
+                        .collect(),
+                }
+            },
+        }
+    }
+}
+
+// Unique passive DNS responses list each resolve with its frequency
+fn unique_table(value: &Value) -> Table {
+    let rows = match value.get("frequency") {
+        Some(&Value::Array(ref items)) => items
+            .iter()
+            .map(|f| vec![cell(f.get(0)), cell(f.get(1))])
+            .collect(),
+        _ => results(value).into_iter().map(|v| vec![cell(Some(v)), String::new()]).collect(),
+    };
+
+    Table {
+        headers: vec![String::from("resolve"), String::from("count")],
+        rows,
+    }
+}
+
+// Use every scalar field found in the results as a column, in the order
+// they are first seen
+fn generic_table(results: &[&Value]) -> Table {
+    let mut headers: Vec<String> = Vec::new();
+    let empty = Map::new();
+    let objects: Vec<&Map<String, Value>> = results
+        .iter()
+        .map(|r| r.as_object().unwrap_or(&empty))
+        .collect();
+
+    for obj in &objects {
+        for (key, value) in obj.iter() {
+            if !value.is_object() && !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+    }
+
+    // Results that are not objects, such as lists of subdomains
+    if headers.is_empty() {
+        return Table {
+            headers: vec![String::from("value")],
+            rows: results.iter().map(|r| vec![cell(Some(r))]).collect(),
+        };
+    }
+
+    let rows = objects
+        .iter()
+        .map(|obj| headers.iter().map(|h| cell(obj.get(h))).collect())
+        .collect();
+    Table { headers, rows }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+impl Table {
+    pub fn write_csv<W: Write>(&self, out: &mut W, header: bool) -> io::Result<()> {
+        if header {
+            writeln!(out, "{}", join_csv(&self.headers))?;
+        }
+        for row in &self.rows {
+            writeln!(out, "{}", join_csv(row))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_table<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (w, c) in widths.iter_mut().zip(row) {
+                *w = cmp::max(*w, c.chars().count());
+            }
+        }
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(c, w)| format!("{:1$}", c, w))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_right()
+                .to_owned()
+        };
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+
+        writeln!(out, "{}", line(&self.headers))?;
+        writeln!(out, "{}", line(&rule))?;
+        for row in &self.rows {
+            writeln!(out, "{}", line(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl Table {
+    // Combine tables into one with a leading `query` column, using the
+    // union of their headers
+    fn tagged<'a, I>(tables: I) -> Table
+    where
+        I: IntoIterator<Item = (&'a str, Table)>,
+    {
+        let mut headers = vec![String::from("query")];
+        let mut rows = Vec::new();
+
+        for (query, table) in tables {
+            for h in &table.headers {
+                if !headers.contains(h) {
+                    headers.push(h.clone());
+                }
+            }
+            for row in table.rows {
+                let mut cells = vec![String::new(); headers.len()];
+                cells[0] = query.to_owned();
+                for (h, c) in table.headers.iter().zip(row) {
+                    let i = headers.iter().position(|x| x == h).unwrap();
+                    cells[i] = c;
+                }
+                rows.push(cells);
+            }
+        }
+
+        // Earlier rows are missing any columns added after them
+        for row in &mut rows {
+            row.resize(headers.len(), String::new());
+        }
+        Table { headers, rows }
+    }
+}
+
+fn join_csv(cells: &[String]) -> String {
+    cells.iter().map(|c| csv_field(c)).collect::<Vec<_>>().join(",")
+}
+
+/// Write a response in the given format
+pub fn write<W: Write>(
+    out: &mut W,
+    format: Format,
+    projection: Projection,
+    value: &Value,
+) -> io::Result<()> {
+    match format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(value)?),
+        Format::Ndjson => {
+            for r in results(value) {
+                writeln!(out, "{}", r)?;
+            }
+            Ok(())
+        },
+        Format::Csv => projection.table(value).write_csv(out, true),
+        Format::Table => projection.table(value).write_table(out),
+    }
+}
+
+// A lookup result as a JSON object tagged with its query
+fn tagged_value(&(ref query, ref result): &Lookup) -> Value {
+    match *result {
+        Ok(ref value) => json!({ "query": query, "response": value }),
+        Err(ref e) => json!({ "query": query, "error": e }),
+    }
+}
+
+/// Write the results of a bulk lookup in the given format, tagging each
+/// result with its query. Failed lookups are left out of the `Csv` and
+/// `Table` formats.
+pub fn write_tagged<W: Write>(
+    out: &mut W,
+    format: Format,
+    projection: Projection,
+    lookups: &[Lookup],
+) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            let values: Vec<Value> = lookups.iter().map(tagged_value).collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&values)?)
+        },
+        Format::Ndjson => {
+            for l in lookups {
+                writeln!(out, "{}", tagged_value(l))?;
+            }
+            Ok(())
+        },
+        Format::Csv | Format::Table => {
+            let table = Table::tagged(lookups.iter().filter_map(|&(ref q, ref r)| {
+                r.as_ref().ok().map(|v| (q.as_str(), projection.table(v)))
+            }));
+            if format == Format::Csv {
+                table.write_csv(out, true)
+            } else {
+                table.write_table(out)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whois() -> Value {
+        json!({
+            "domain": "example.com",
+            "registrar": "Registrar, Inc.",
+            "registrant": { "email": "owner@example.com" },
+            "nameServers": ["ns1.example.com", "ns2.example.com"],
+        })
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn path_lookup() {
+        let value = whois();
+        assert_eq!(path(&value, "domain"), Some(&json!("example.com")));
+        assert_eq!(path(&value, "registrant/email"), Some(&json!("owner@example.com")));
+        assert_eq!(path(&value, "registrant/name"), None);
+        assert_eq!(path(&value, "domain/email"), None);
+        assert_eq!(path(&value, "missing/email"), None);
+    }
+
+    #[test]
+    fn missing_fields_are_empty() {
+        let table = Projection::Whois.table(&whois());
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(
+            table.rows[0],
+            vec![
+                "example.com",
+                "Registrar, Inc.",
+                "",
+                "",
+                "",
+                "owner@example.com",
+                "ns1.example.com;ns2.example.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_output() {
+        let mut out = Vec::new();
+        write(&mut out, Format::Csv, Projection::Whois, &whois()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "domain,registrar,registered,updated,expires,registrantEmail,nameServers\n\
+             example.com,\"Registrar, Inc.\",,,,owner@example.com,ns1.example.com;ns2.example.com\n"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Groups of API endpoints that share a cache TTL
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EndpointFamily {
+    /// Passive DNS, cached for 15 minutes by default
+    PassiveDns,
+    /// WHOIS lookups and searches, cached for 7 days by default
+    Whois,
+    /// SSL certificates, history and searches, cached for 1 day by default
+    Ssl,
+    /// Enrichment data, OSINT, malware and subdomains, cached for 1 hour by
+    /// default
+    Enrichment,
+    /// Action flags such as tags and classifications, cached for 5 minutes
+    /// by default
+    Actions,
+    /// Account details, history, monitors and sources, cached for 5 minutes
+    /// by default
+    Account,
+    /// Projects, cached for 5 minutes by default
+    Projects,
+    /// Intelligence articles, cached for 1 hour by default
+    Articles,
+    /// Monitor alerts, cached for 1 minute by default
+    Monitor,
+    /// Account quota, never cached by default
+    Quota,
+    /// Organization teamstream, never cached by default as it is polled for
+    /// new events
+    Teamstream,
+}
+
+impl EndpointFamily {
+    /// Returns the family of an API endpoint path such as `/dns/passive`
+    pub fn from_endpoint(endpoint: &str) -> EndpointFamily {
+        let endpoint = endpoint.trim_left_matches('/');
+        if endpoint.starts_with("dns/") {
+            EndpointFamily::PassiveDns
+        } else if endpoint.starts_with("whois") {
+            EndpointFamily::Whois
+        } else if endpoint.starts_with("ssl-certificate") {
+            EndpointFamily::Ssl
+        } else if endpoint.starts_with("enrichment") {
+            EndpointFamily::Enrichment
+        } else if endpoint.starts_with("actions") {
+            EndpointFamily::Actions
+        } else if endpoint.starts_with("project") {
+            EndpointFamily::Projects
+        } else if endpoint.starts_with("articles") {
+            EndpointFamily::Articles
+        } else if endpoint.starts_with("monitor") {
+            EndpointFamily::Monitor
+        } else if endpoint.starts_with("account/quota") {
+            EndpointFamily::Quota
+        } else if endpoint.starts_with("account/organization/teamstream") {
+            EndpointFamily::Teamstream
+        } else {
+            EndpointFamily::Account
+        }
+    }
+
+    // The TTL used unless one is set with `ResponseCache::ttl`
+    pub(crate) fn default_ttl(&self) -> Option<Duration> {
+        let secs = match *self {
+            EndpointFamily::PassiveDns => 15 * MINUTE,
+            EndpointFamily::Whois => 7 * DAY,
+            EndpointFamily::Ssl => DAY,
+            EndpointFamily::Enrichment | EndpointFamily::Articles => HOUR,
+            EndpointFamily::Actions | EndpointFamily::Account | EndpointFamily::Projects => {
+                5 * MINUTE
+            },
+            EndpointFamily::Monitor => MINUTE,
+            EndpointFamily::Quota | EndpointFamily::Teamstream => return None,
+        };
+        Some(Duration::from_secs(secs))
+    }
+}
+
+/// Counters describing how well a `ResponseCache` is doing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Requests answered from the cache
+    pub hits: u64,
+    /// Cacheable requests that were sent to the API
+    pub misses: u64,
+    /// Responses currently cached
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    stored: Instant,
+    ttl: Duration,
+    // Position of this entry in `Entries::order`
+    used: u64,
+}
+
+// Cached responses along with the order they were last used in, oldest
+// first, for LRU eviction, and the hit and miss counters
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.map.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+
+    // Mark an entry as the most recently used
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.map.get_mut(key) {
+            self.order.remove(&entry.used);
+            entry.used = self.tick;
+            self.order.insert(self.tick, String::from(key));
+        }
+    }
+}
+
+/// An in-memory cache of API responses with a TTL per `EndpointFamily`.
+///
+/// Responses are keyed by endpoint and request parameters. Once the cache
+/// holds `capacity` responses the least recently used one is evicted to make
+/// room for a new one. Only successful responses are cached.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use passivetotal_reqwest::{EndpointFamily, PassiveTotal, ResponseCache};
+///
+/// let cache = ResponseCache::new(10_000)
+///     .ttl(EndpointFamily::PassiveDns, Some(Duration::from_secs(300)));
+/// let pt = PassiveTotal::with_auth("username", "apikey").with_cache(cache);
+/// ```
+#[derive(Debug)]
+pub struct ResponseCache {
+    capacity: usize,
+    ttls: HashMap<EndpointFamily, Option<Duration>>,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    /// Create a new cache holding at most `capacity` responses, using the
+    /// default TTL of each `EndpointFamily`
+    pub fn new(capacity: usize) -> ResponseCache {
+        ResponseCache {
+            capacity,
+            ttls: HashMap::new(),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Set how long responses from an `EndpointFamily` are cached for.
+    /// `None` disables caching for the family.
+    pub fn ttl(mut self, family: EndpointFamily, ttl: Option<Duration>) -> ResponseCache {
+        self.ttls.insert(family, ttl);
+        self
+    }
+
+    /// Returns the hit and miss counters and the number of cached responses
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.lock();
+        CacheStats {
+            hits: entries.hits,
+            misses: entries.misses,
+            entries: entries.map.len(),
+        }
+    }
+
+    /// Remove every cached response. The counters are not reset.
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.map.clear();
+        entries.order.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn ttl_for(&self, endpoint: &str) -> Option<Duration> {
+        let family = EndpointFamily::from_endpoint(endpoint);
+        match self.ttls.get(&family) {
+            Some(ttl) => *ttl,
+            None => family.default_ttl(),
+        }
+    }
+
+    // Returns the cached response for `key` if it has not expired. Requests
+    // to endpoints that are not cached are not counted as misses.
+    pub(crate) fn get(&self, endpoint: &str, key: &str) -> Option<Value> {
+        if self.capacity == 0 || self.ttl_for(endpoint).is_none() {
+            return None;
+        }
+
+        let mut entries = self.lock();
+        let value = match entries.map.get(key) {
+            Some(entry) if entry.stored.elapsed() < entry.ttl => Some(entry.value.clone()),
+            _ => None,
+        };
+
+        match value {
+            Some(value) => {
+                entries.touch(key);
+                entries.hits += 1;
+                Some(value)
+            },
+            None => {
+                entries.remove(key);
+                entries.misses += 1;
+                None
+            },
+        }
+    }
+
+    pub(crate) fn insert(&self, endpoint: &str, key: String, value: &Value) {
+        let ttl = match self.ttl_for(endpoint) {
+            Some(ttl) if self.capacity > 0 => ttl,
+            _ => return,
+        };
+
+        let mut entries = self.lock();
+        entries.remove(&key);
+        while entries.map.len() >= self.capacity {
+            let oldest = match entries.order.keys().next() {
+                Some(&used) => entries.order.remove(&used).unwrap(),
+                None => break,
+            };
+            entries.map.remove(&oldest);
+        }
+
+        entries.map.insert(
+            key.clone(),
+            Entry {
+                value: value.clone(),
+                stored: Instant::now(),
+                ttl,
+                used: 0,
+            },
+        );
+        entries.touch(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_families() {
+        let families = [
+            ("/dns/passive", EndpointFamily::PassiveDns),
+            ("/whois/search", EndpointFamily::Whois),
+            ("/ssl-certificate/history", EndpointFamily::Ssl),
+            ("/enrichment/subdomains", EndpointFamily::Enrichment),
+            ("/actions/tags", EndpointFamily::Actions),
+            ("/project", EndpointFamily::Projects),
+            ("/articles", EndpointFamily::Articles),
+            ("/monitor", EndpointFamily::Monitor),
+            ("/account/monitors", EndpointFamily::Account),
+            ("/account/quota", EndpointFamily::Quota),
+            ("/account/organization/teamstream", EndpointFamily::Teamstream),
+        ];
+        for &(endpoint, family) in &families {
+            assert_eq!(EndpointFamily::from_endpoint(endpoint), family, "{}", endpoint);
+        }
+    }
+
+    #[test]
+    fn huge_ttl_never_expires() {
+        let ttl = Duration::from_secs(::std::u64::MAX);
+        let cache = ResponseCache::new(10).ttl(EndpointFamily::Whois, Some(ttl));
+        let value = Value::from("response");
+        cache.insert("/whois", String::from("key"), &value);
+        assert_eq!(cache.get("/whois", "key"), Some(value));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ResponseCache::new(2);
+        for key in &["a", "b"] {
+            cache.insert("/whois", String::from(*key), &Value::from(*key));
+        }
+
+        // Using `a` leaves `b` as the least recently used
+        assert!(cache.get("/whois", "a").is_some());
+        cache.insert("/whois", String::from("c"), &Value::from("c"));
+
+        assert_eq!(cache.get("/whois", "a"), Some(Value::from("a")));
+        assert_eq!(cache.get("/whois", "b"), None);
+        assert_eq!(cache.get("/whois", "c"), Some(Value::from("c")));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn entries_expire() {
+        let ttl = Duration::from_millis(50);
+        let cache = ResponseCache::new(10).ttl(EndpointFamily::PassiveDns, Some(ttl));
+        cache.insert("/dns/passive", String::from("key"), &Value::from("response"));
+        assert!(cache.get("/dns/passive", "key").is_some());
+
+        ::std::thread::sleep(ttl * 2);
+        assert_eq!(cache.get("/dns/passive", "key"), None);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let cache = ResponseCache::new(10);
+        assert_eq!(cache.get("/whois", "key"), None);
+        cache.insert("/whois", String::from("key"), &Value::from("response"));
+        assert!(cache.get("/whois", "key").is_some());
+        assert!(cache.get("/whois", "key").is_some());
+
+        // Endpoints that are not cached are not counted
+        assert_eq!(cache.get("/account/quota", "key"), None);
+        cache.insert("/account/organization/teamstream", String::from("ts"), &Value::Null);
+        assert_eq!(cache.get("/account/organization/teamstream", "ts"), None);
+
+        let stats = CacheStats {
+            hits: 2,
+            misses: 1,
+            entries: 1,
+        };
+        assert_eq!(cache.stats(), stats);
+
+        // Clearing keeps the counters
+        cache.clear();
+        assert_eq!(cache.stats(), CacheStats { entries: 0, ..stats });
+    }
+}
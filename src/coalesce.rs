@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use serde_json::Value;
+
+use {PassiveTotalError, Result};
+
+// A request that is currently being sent, shared by every caller that asked
+// for it while it was in flight
+#[derive(Debug, Default)]
+struct Call {
+    result: Mutex<Option<Result<Value>>>,
+    done: Condvar,
+}
+
+/// Coalesces identical in-flight requests so that only one is sent and
+/// every caller receives its result.
+#[derive(Debug, Default)]
+pub struct Coalescer {
+    inflight: Mutex<HashMap<String, Arc<Call>>>,
+}
+
+// Removes a call from the in-flight map when its leader finishes, even if
+// sending the request panicked, so waiters are never left blocked
+struct Leader<'a> {
+    coalescer: &'a Coalescer,
+    key: &'a str,
+    call: &'a Call,
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Copy a result for a waiting caller. Errors that can not be cloned are
+// passed on as a `CoalescedError` with the original message.
+fn share(result: &Result<Value>) -> Result<Value> {
+    match *result {
+        Ok(ref value) => Ok(value.clone()),
+        Err(ref err) => Err(match *err {
+            PassiveTotalError::InvalidDomain => PassiveTotalError::InvalidDomain,
+            PassiveTotalError::InvalidQuery(ref q, ref kinds) => {
+                PassiveTotalError::InvalidQuery(q.clone(), kinds.clone())
+            },
+            PassiveTotalError::ClientError(code) => PassiveTotalError::ClientError(code),
+            PassiveTotalError::ServerError(code) => PassiveTotalError::ServerError(code),
+            PassiveTotalError::CredentialError(ref s) => {
+                PassiveTotalError::CredentialError(s.clone())
+            },
+            ref err => PassiveTotalError::CoalescedError(err.to_string()),
+        }),
+    }
+}
+
+impl Coalescer {
+    /// Run `send` for the request identified by `key`, unless an identical
+    /// request is already in flight, in which case wait for its result.
+    pub fn run<F>(&self, key: String, send: F) -> Result<Value>
+    where
+        F: FnOnce() -> Result<Value>,
+    {
+        let (call, leader) = {
+            let mut inflight = lock(&self.inflight);
+            match inflight.get(&key) {
+                Some(call) => (call.clone(), false),
+                None => {
+                    let call = Arc::new(Call::default());
+                    inflight.insert(key.clone(), call.clone());
+                    (call, true)
+                },
+            }
+        };
+
+        if !leader {
+            let mut result = lock(&call.result);
+            while result.is_none() {
+                result = call.done.wait(result).unwrap_or_else(|e| e.into_inner());
+            }
+            return share(result.as_ref().unwrap());
+        }
+
+        let _leader = Leader {
+            coalescer: self,
+            key: &key,
+            call: &call,
+        };
+        let result = send();
+        *lock(&call.result) = Some(share(&result));
+        result
+    }
+}
+
+impl<'a> Drop for Leader<'a> {
+    fn drop(&mut self) {
+        lock(&self.coalescer.inflight).remove(self.key);
+
+        let mut result = lock(&self.call.result);
+        if result.is_none() {
+            *result = Some(Err(PassiveTotalError::CoalescedError(String::from(
+                "the coalesced request did not complete",
+            ))));
+        }
+        self.call.done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::Duration;
+
+    const CALLERS: usize = 8;
+
+    // Start `CALLERS` threads that all run `send` for the same key at the
+    // same time, returning their results
+    fn run_together<F>(coalescer: &Arc<Coalescer>, send: F) -> Vec<thread::Result<Result<Value>>>
+    where
+        F: Fn() -> Result<Value> + Send + Sync + 'static,
+    {
+        let send = Arc::new(send);
+        let barrier = Arc::new(Barrier::new(CALLERS));
+        let callers: Vec<_> = (0..CALLERS)
+            .map(|_| {
+                let (coalescer, send, barrier) = (coalescer.clone(), send.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    coalescer.run(String::from("key"), || {
+                        // Give every other caller time to find the request
+                        thread::sleep(Duration::from_millis(200));
+                        send()
+                    })
+                })
+            })
+            .collect();
+        callers.into_iter().map(|c| c.join()).collect()
+    }
+
+    #[test]
+    fn identical_requests_sent_once() {
+        let coalescer = Arc::new(Coalescer::default());
+        let sent = Arc::new(AtomicUsize::new(0));
+        let count = sent.clone();
+
+        let results = run_together(&coalescer, move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"results": ["1.2.3.4"]}))
+        });
+
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        for result in results {
+            assert_eq!(result.unwrap().unwrap(), json!({"results": ["1.2.3.4"]}));
+        }
+        assert!(lock(&coalescer.inflight).is_empty());
+    }
+
+    #[test]
+    fn errors_are_shared() {
+        let coalescer = Arc::new(Coalescer::default());
+        let results = run_together(&coalescer, || {
+            Err(PassiveTotalError::CredentialError(String::from("bad key")))
+        });
+
+        for result in results {
+            match result.unwrap() {
+                Err(PassiveTotalError::CredentialError(ref msg)) => assert_eq!(msg, "bad key"),
+                other => panic!("expected a CredentialError, got {:?}", other),
+            }
+        }
+        assert!(lock(&coalescer.inflight).is_empty());
+    }
+
+    #[test]
+    fn leader_panic_wakes_waiters() {
+        let coalescer = Arc::new(Coalescer::default());
+        let results = run_together(&coalescer, || panic!("send panicked"));
+
+        let mut panicked = 0;
+        for result in results {
+            match result {
+                Err(_) => panicked += 1,
+                Ok(Err(PassiveTotalError::CoalescedError(_))) => {},
+                Ok(other) => panic!("expected a CoalescedError, got {:?}", other),
+            }
+        }
+        assert_eq!(panicked, 1);
+        assert!(lock(&coalescer.inflight).is_empty());
+
+        // The next request is sent again
+        assert_eq!(coalescer.run(String::from("key"), || Ok(json!(1))).unwrap(), json!(1));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use dirs;
+use toml;
+
+use {secret, PassiveTotal, PassiveTotalError, Result, Secret, BASE_URL};
+
+const CONFIG_FILE: &str = ".passivetotal.toml";
+const ENV_USERNAME: &str = "PT_USERNAME";
+const ENV_APIKEY: &str = "PT_APIKEY";
+const ENV_TIMEOUT: &str = "PT_TIMEOUT";
+const DEFAULT_TIMEOUT: u64 = 60;
+
+/// Configuration used to create a `PassiveTotal` client.
+///
+/// Configuration files use the following TOML format. The `[passivetotal]`
+/// table is the default profile and each `[profile.NAME]` table is a named
+/// profile. Only `username` and `apikey` are required. `timeout` is in
+/// seconds and `rate_limit` is the maximum number of requests per minute.
+///
+/// ```toml
+/// [passivetotal]
+/// username = "USERNAME"
+/// apikey = "API_KEY"
+/// timeout = 60
+///
+/// [profile.automation]
+/// username = "AUTOMATION_USERNAME"
+/// apikey = "AUTOMATION_API_KEY"
+/// base_url = "https://api.passivetotal.org/v2"
+/// rate_limit = 30
+/// ```
+///
+/// Named profiles do not inherit values from the default profile or from
+/// the `PT_USERNAME` and `PT_APIKEY` environment variables, so credentials
+/// are never shared between profiles by accident.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub username: String,
+    pub apikey: Secret,
+    pub timeout: Duration,
+    pub base_url: String,
+    pub rate_limit: Option<u32>,
+}
+
+// Configuration values read from a single source, merged by `Config::load`
+#[derive(Default, Deserialize)]
+struct PartialConfig {
+    username: Option<String>,
+    apikey: Option<Secret>,
+    timeout: Option<u64>,
+    base_url: Option<String>,
+    rate_limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    passivetotal: Option<PartialConfig>,
+    #[serde(default)]
+    profile: HashMap<String, PartialConfig>,
+}
+
+impl PartialConfig {
+    fn from_file(path: &Path, profile: Option<&str>) -> Result<PartialConfig> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| {
+                PassiveTotalError::ConfigError(format!("Unable to read {}: {}", path.display(), e))
+            })?;
+        let parsed = toml::from_str(&contents);
+        secret::zero(&mut contents);
+        let mut file: ConfigFile = parsed.map_err(|e| {
+            PassiveTotalError::ConfigError(format!("Unable to parse {}: {}", path.display(), e))
+        })?;
+
+        match profile {
+            Some(name) => file.profile.remove(name).ok_or_else(|| {
+                PassiveTotalError::ConfigError(format!(
+                    "No profile {} in {}",
+                    name,
+                    path.display()
+                ))
+            }),
+            None => Ok(file.passivetotal.unwrap_or_default()),
+        }
+    }
+
+    fn from_env() -> Result<PartialConfig> {
+        let timeout = match env::var(ENV_TIMEOUT) {
+            Ok(timeout) => Some(timeout.parse().map_err(|_| {
+                PassiveTotalError::ConfigError(format!("Invalid {}: {}", ENV_TIMEOUT, timeout))
+            })?),
+            Err(_) => None,
+        };
+
+        Ok(PartialConfig {
+            username: env::var(ENV_USERNAME).ok(),
+            apikey: env::var(ENV_APIKEY).ok().map(Secret::new),
+            timeout,
+            ..PartialConfig::default()
+        })
+    }
+
+    // Fill in any values missing from `self` with those from `other`. The
+    // username and API key are taken as a pair so a key is never used with
+    // the username from another source.
+    fn or(self, other: PartialConfig) -> PartialConfig {
+        let (username, apikey) = if self.username.is_some() || self.apikey.is_some() {
+            (self.username, self.apikey)
+        } else {
+            (other.username, other.apikey)
+        };
+
+        PartialConfig {
+            username,
+            apikey,
+            timeout: self.timeout.or(other.timeout),
+            base_url: self.base_url.or(other.base_url),
+            rate_limit: self.rate_limit.or(other.rate_limit),
+        }
+    }
+
+    fn into_config(self) -> Result<Config> {
+        let missing = |name| PassiveTotalError::ConfigError(format!("No {} configured", name));
+
+        if self.rate_limit == Some(0) {
+            return Err(PassiveTotalError::ConfigError(String::from(
+                "rate_limit must be greater than 0",
+            )));
+        }
+
+        Ok(Config {
+            username: self.username.ok_or_else(|| missing("username"))?,
+            apikey: self.apikey.ok_or_else(|| missing("apikey"))?,
+            timeout: Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT)),
+            base_url: self.base_url.unwrap_or_else(|| String::from(BASE_URL)),
+            rate_limit: self.rate_limit,
+        })
+    }
+}
+
+impl Config {
+    /// Create a new `Config` with the default timeout of 60 seconds.
+    pub fn new<S>(username: S, apikey: S) -> Config
+    where
+        S: Into<String>,
+    {
+        Config {
+            username: username.into(),
+            apikey: Secret::new(apikey),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            base_url: String::from(BASE_URL),
+            rate_limit: None,
+        }
+    }
+
+    /// The default configuration file, `$HOME/.passivetotal.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(CONFIG_FILE))
+    }
+
+    /// Load the default profile from the environment and configuration files.
+    ///
+    /// Values are taken from the following sources, in order of precedence:
+    ///
+    /// 1. The `PT_USERNAME`, `PT_APIKEY` and `PT_TIMEOUT` environment
+    ///    variables. The username and API key are taken together from
+    ///    whichever source sets either of them.
+    /// 2. The configuration file at `path` if one is given. Otherwise
+    ///    `$HOME/.passivetotal.toml` if it exists.
+    /// 3. The default timeout of 60 seconds.
+    ///
+    /// Returns a `ConfigError` if no username or API key is found or if the
+    /// file at `path` can not be read.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        Config::load_profile(path, None)
+    }
+
+    /// Load a named profile, or the default profile if `profile` is `None`.
+    ///
+    /// Uses the same sources and precedence as `load`, except that a named
+    /// profile always takes its username and API key from the file and
+    /// ignores `PT_USERNAME` and `PT_APIKEY`. Returns a `ConfigError` if the
+    /// configuration file has no such profile.
+    pub fn load_profile(path: Option<&Path>, profile: Option<&str>) -> Result<Config> {
+        let file = match path {
+            Some(path) => PartialConfig::from_file(path, profile)?,
+            None => match Config::default_path() {
+                Some(ref path) if path.exists() => PartialConfig::from_file(path, profile)?,
+                _ if profile.is_some() => {
+                    return Err(PassiveTotalError::ConfigError(String::from(
+                        "No configuration file to load profile from",
+                    )))
+                },
+                _ => PartialConfig::default(),
+            },
+        };
+
+        let mut env = PartialConfig::from_env()?;
+        if profile.is_some() {
+            env.username = None;
+            env.apikey = None;
+        }
+
+        env.or(file).into_config()
+    }
+
+    /// Load the default profile from the given file only.
+    pub fn from_file<P>(path: P) -> Result<Config>
+    where
+        P: AsRef<Path>,
+    {
+        PartialConfig::from_file(path.as_ref(), None)?.into_config()
+    }
+
+    /// Load the configuration from the environment variables only.
+    pub fn from_env() -> Result<Config> {
+        PartialConfig::from_env()?.into_config()
+    }
+}
+
+impl PassiveTotal {
+    /// Create a new PassiveTotal struct from a `Config`.
+    pub fn from_config(config: &Config) -> Self {
+        let pt = PassiveTotal::new(
+            config.username.as_str(),
+            config.apikey.expose(),
+            config.timeout,
+        ).with_base_url(config.base_url.as_str());
+
+        match config.rate_limit {
+            Some(limit) => pt.with_rate_limit(limit),
+            None => pt,
+        }
+    }
+
+    /// Create a new PassiveTotal struct from a named profile in
+    /// `$HOME/.passivetotal.toml`.
+    ///
+    /// See `Config::load_profile` for details.
+    pub fn from_profile(profile: &str) -> Result<Self> {
+        Config::load_profile(None, Some(profile)).map(|config| PassiveTotal::from_config(&config))
+    }
+}
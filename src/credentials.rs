@@ -0,0 +1,240 @@
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use {secret, PassiveTotalError, Result, Secret};
+
+const ENV_USERNAME: &str = "PT_USERNAME";
+const ENV_APIKEY: &str = "PT_APIKEY";
+
+/// A username and API key used to authenticate with the Passivetotal API
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub username: String,
+    pub apikey: Secret,
+}
+
+/// A source of Passivetotal API credentials.
+///
+/// `PassiveTotal` asks its provider for credentials before its first request
+/// and again whenever a request is rejected with a 401, so providers that
+/// read from an external source pick up rotated keys without a restart.
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    /// Fetch the current credentials
+    fn credentials(&self) -> Result<Credentials>;
+}
+
+/// Credentials that never change
+#[derive(Clone, Debug)]
+pub struct StaticCredentials {
+    credentials: Credentials,
+}
+
+/// Credentials read from environment variables, `PT_USERNAME` and
+/// `PT_APIKEY` by default
+#[derive(Clone, Debug)]
+pub struct EnvCredentials {
+    username_var: String,
+    apikey_var: String,
+}
+
+/// An API key read from a file, such as a secret mounted by a container
+/// orchestrator.
+///
+/// Leading and trailing whitespace is removed from the file contents.
+#[derive(Clone, Debug)]
+pub struct FileCredentials {
+    username: String,
+    apikey_path: PathBuf,
+}
+
+/// Credentials printed by an external command, such as a password manager.
+///
+/// The command must print `username=USERNAME` and `apikey=API_KEY` lines to
+/// stdout and exit successfully.
+#[derive(Clone, Debug)]
+pub struct CommandCredentials {
+    program: String,
+    args: Vec<String>,
+}
+
+fn credential_error<S>(msg: S) -> PassiveTotalError
+where
+    S: Into<String>,
+{
+    PassiveTotalError::CredentialError(msg.into())
+}
+
+impl Credentials {
+    pub fn new<S>(username: S, apikey: S) -> Credentials
+    where
+        S: Into<String>,
+    {
+        Credentials {
+            username: username.into(),
+            apikey: Secret::new(apikey),
+        }
+    }
+
+    // Check if two sets of credentials are the same
+    pub(crate) fn same_as(&self, other: &Credentials) -> bool {
+        self.username == other.username && self.apikey.expose() == other.apikey.expose()
+    }
+}
+
+impl StaticCredentials {
+    pub fn new<S>(username: S, apikey: S) -> StaticCredentials
+    where
+        S: Into<String>,
+    {
+        StaticCredentials {
+            credentials: Credentials::new(username, apikey),
+        }
+    }
+}
+
+impl CredentialProvider for StaticCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(self.credentials.clone())
+    }
+}
+
+impl EnvCredentials {
+    /// Read credentials from `PT_USERNAME` and `PT_APIKEY`
+    pub fn new() -> EnvCredentials {
+        EnvCredentials::with_vars(ENV_USERNAME, ENV_APIKEY)
+    }
+
+    /// Read credentials from the given environment variables
+    pub fn with_vars<S>(username_var: S, apikey_var: S) -> EnvCredentials
+    where
+        S: Into<String>,
+    {
+        EnvCredentials {
+            username_var: username_var.into(),
+            apikey_var: apikey_var.into(),
+        }
+    }
+}
+
+impl Default for EnvCredentials {
+    fn default() -> EnvCredentials {
+        EnvCredentials::new()
+    }
+}
+
+impl CredentialProvider for EnvCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let var = |name: &str| {
+            env::var(name).map_err(|_| credential_error(format!("{} is not set", name)))
+        };
+
+        Ok(Credentials {
+            username: var(&self.username_var)?,
+            apikey: Secret::new(var(&self.apikey_var)?),
+        })
+    }
+}
+
+impl FileCredentials {
+    pub fn new<S, P>(username: S, apikey_path: P) -> FileCredentials
+    where
+        S: Into<String>,
+        P: AsRef<Path>,
+    {
+        FileCredentials {
+            username: username.into(),
+            apikey_path: apikey_path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let mut contents = String::new();
+        File::open(&self.apikey_path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| {
+                credential_error(format!(
+                    "Unable to read {}: {}",
+                    self.apikey_path.display(),
+                    e
+                ))
+            })?;
+        let apikey = Secret::new(contents.trim());
+        secret::zero(&mut contents);
+
+        if apikey.expose().is_empty() {
+            return Err(credential_error(format!(
+                "{} is empty",
+                self.apikey_path.display()
+            )));
+        }
+
+        Ok(Credentials {
+            username: self.username.clone(),
+            apikey,
+        })
+    }
+}
+
+impl CommandCredentials {
+    pub fn new<S>(program: S) -> CommandCredentials
+    where
+        S: Into<String>,
+    {
+        CommandCredentials {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Add an argument to pass to the command
+    pub fn arg<S>(mut self, arg: S) -> CommandCredentials
+    where
+        S: Into<String>,
+    {
+        self.args.push(arg.into());
+        self
+    }
+}
+
+impl CredentialProvider for CommandCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let output = Command::new(&self.program)
+            .args(&self.args)
+            .output()
+            .map_err(|e| credential_error(format!("Unable to run {}: {}", self.program, e)))?;
+        if !output.status.success() {
+            return Err(credential_error(format!(
+                "{} failed: {}",
+                self.program,
+                output.status
+            )));
+        }
+
+        let mut stdout = String::from_utf8(output.stdout)
+            .map_err(|_| credential_error(format!("{} printed invalid UTF-8", self.program)))?;
+        let mut username = None;
+        let mut apikey = None;
+        for line in stdout.lines() {
+            if line.starts_with("username=") {
+                username = Some(line["username=".len()..].trim().to_owned());
+            } else if line.starts_with("apikey=") {
+                apikey = Some(Secret::new(line["apikey=".len()..].trim()));
+            }
+        }
+        secret::zero(&mut stdout);
+
+        match (username, apikey) {
+            (Some(username), Some(apikey)) => Ok(Credentials { username, apikey }),
+            _ => Err(credential_error(format!(
+                "{} did not print a username and apikey",
+                self.program
+            ))),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, TimeZone, Utc};
+use dirs;
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use {EndpointFamily, PassiveTotalError, Result};
+
+const CACHE_DIR: &str = "passivetotal";
+const EXTENSION: &str = "json";
+
+// A response as stored on disk
+#[derive(Serialize, Deserialize)]
+struct StoredResponse {
+    #[serde(default)]
+    account: String,
+    endpoint: String,
+    params: String,
+    stored: u64,
+    response: Value,
+}
+
+/// Details of a response stored in a `DiskCache`
+#[derive(Clone, Debug)]
+pub struct DiskCacheEntry {
+    /// The account and API the response was stored for
+    pub account: String,
+    /// The API endpoint, such as `/dns/passive`
+    pub endpoint: String,
+    /// The request parameters as JSON
+    pub params: String,
+    /// When the response was stored
+    pub stored: DateTime<Utc>,
+    /// When the response expires, `None` if its endpoint is no longer cached
+    pub expires: Option<DateTime<Utc>>,
+    /// Size of the stored response in bytes
+    pub size: u64,
+}
+
+/// A cache of API responses stored on disk so they can be shared between
+/// runs and processes.
+///
+/// Responses are stored one per file in the cache directory and expire
+/// using the same `EndpointFamily` TTLs as `ResponseCache`. Each response
+/// is stored for an account, which `PassiveTotal` sets to its username and
+/// base URL so that responses are never shared between accounts or APIs.
+/// Files are only readable by their owner on Unix. Errors reading
+/// or writing the cache while sending a request are ignored and the request
+/// is sent to the API instead.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{DiskCache, PassiveTotal};
+///
+/// let cache = DiskCache::open(DiskCache::default_dir().unwrap()).unwrap();
+/// let pt = PassiveTotal::with_auth("username", "apikey").with_disk_cache(cache);
+/// ```
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttls: HashMap<EndpointFamily, Option<Duration>>,
+    refresh: bool,
+}
+
+// FNV-1a, used to name cache files. Unlike `DefaultHasher` it is stable
+// between Rust versions, so files stay valid after an upgrade.
+pub(crate) fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn timestamp(secs: u64) -> DateTime<Utc> {
+    Utc.timestamp_opt(secs as i64, 0).single().unwrap_or_else(Utc::now)
+}
+
+fn cache_error(path: &Path, e: &fmt::Display) -> PassiveTotalError {
+    PassiveTotalError::CacheError(format!("{}: {}", path.display(), e))
+}
+
+impl DiskCache {
+    /// The default cache directory, `passivetotal` under the user's cache
+    /// directory, such as `~/.cache/passivetotal` on Linux
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join(CACHE_DIR))
+    }
+
+    /// Open the cache in `dir`, creating the directory if needed
+    pub fn open<P>(dir: P) -> Result<DiskCache>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| cache_error(&dir, &e))?;
+        Ok(DiskCache {
+            dir,
+            ttls: HashMap::new(),
+            refresh: false,
+        })
+    }
+
+    /// Set how long responses from an `EndpointFamily` are cached for.
+    /// `None` disables caching for the family.
+    pub fn ttl(mut self, family: EndpointFamily, ttl: Option<Duration>) -> DiskCache {
+        self.ttls.insert(family, ttl);
+        self
+    }
+
+    /// Ignore cached responses and send every request to the API, storing
+    /// the fresh responses
+    pub fn refresh(mut self, refresh: bool) -> DiskCache {
+        self.refresh = refresh;
+        self
+    }
+
+    /// The directory responses are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn ttl_for(&self, endpoint: &str) -> Option<Duration> {
+        let family = EndpointFamily::from_endpoint(endpoint);
+        match self.ttls.get(&family) {
+            Some(ttl) => *ttl,
+            None => family.default_ttl(),
+        }
+    }
+
+    fn path(&self, account: &str, endpoint: &str, params: &str) -> PathBuf {
+        let key = format!("{} {} {}", account, endpoint, params);
+        self.dir.join(format!("{:016x}.{}", fnv1a(&key), EXTENSION))
+    }
+
+    fn read(path: &Path) -> Result<StoredResponse> {
+        let file = File::open(path).map_err(|e| cache_error(path, &e))?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| cache_error(path, &e))
+    }
+
+    // Returns when a stored response expires, if its endpoint is cached
+    fn expires(&self, stored: &StoredResponse) -> Option<u64> {
+        self.ttl_for(&stored.endpoint)
+            .map(|ttl| stored.stored.saturating_add(ttl.as_secs()))
+    }
+
+    // Returns the stored response for a request, whether or not it has
+    // expired
+    fn read_matching(&self, account: &str, endpoint: &str, params: &str) -> Option<StoredResponse> {
+        let stored = DiskCache::read(&self.path(account, endpoint, params)).ok()?;
+        if stored.account == account && stored.endpoint == endpoint && stored.params == params {
+            Some(stored)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stored response for a request from `account` if it has
+    /// not expired.
+    ///
+    /// Unlike requests sent through `PassiveTotal` this ignores `refresh`.
+    pub fn get(&self, account: &str, endpoint: &str, params: &str) -> Option<Value> {
+        let stored = self.read_matching(account, endpoint, params)?;
+        if self.expires(&stored).map_or(false, |expires| expires > now()) {
+            Some(stored.response)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stored response for a request from `account` even if it
+    /// has expired
+    pub fn get_stale(&self, account: &str, endpoint: &str, params: &str) -> Option<Value> {
+        self.read_matching(account, endpoint, params)
+            .map(|stored| stored.response)
+    }
+
+    // Returns the stored response for a request made through `PassiveTotal`
+    pub(crate) fn lookup(&self, account: &str, endpoint: &str, params: &str) -> Option<Value> {
+        if self.refresh {
+            None
+        } else {
+            self.get(account, endpoint, params)
+        }
+    }
+
+    /// Store the response to a request from `account`, if its endpoint is
+    /// cached.
+    ///
+    /// The response is written to a temporary file first so that other
+    /// processes never read a partly written response.
+    pub fn insert(
+        &self,
+        account: &str,
+        endpoint: &str,
+        params: &str,
+        response: &Value,
+    ) -> Result<()> {
+        if self.ttl_for(endpoint).is_none() {
+            return Ok(());
+        }
+
+        let path = self.path(account, endpoint, params);
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        let stored = StoredResponse {
+            account: String::from(account),
+            endpoint: String::from(endpoint),
+            params: String::from(params),
+            stored: now(),
+            response: response.clone(),
+        };
+
+        write_atomic(&tmp, &path, &stored).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            cache_error(&path, &e)
+        })
+    }
+
+    // Paths of every stored response
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let dir = fs::read_dir(&self.dir).map_err(|e| cache_error(&self.dir, &e))?;
+        Ok(dir
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map_or(false, |ext| ext == EXTENSION))
+            .collect())
+    }
+
+    /// List the stored responses, oldest first. Files that can not be read
+    /// are skipped.
+    pub fn entries(&self) -> Result<Vec<DiskCacheEntry>> {
+        let mut entries: Vec<DiskCacheEntry> = self
+            .files()?
+            .into_iter()
+            .filter_map(|path| {
+                let size = fs::metadata(&path).ok()?.len();
+                let stored = DiskCache::read(&path).ok()?;
+                Some(DiskCacheEntry {
+                    expires: self.expires(&stored).map(timestamp),
+                    stored: timestamp(stored.stored),
+                    account: stored.account,
+                    endpoint: stored.endpoint,
+                    params: stored.params,
+                    size,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|e| e.stored);
+        Ok(entries)
+    }
+
+    /// Remove stored responses, only those that have expired or can not be
+    /// read if `expired_only` is `true`. Returns the number removed.
+    pub fn purge(&self, expired_only: bool) -> Result<usize> {
+        let now = now();
+        let mut removed = 0;
+        for path in self.files()? {
+            if expired_only {
+                let fresh = DiskCache::read(&path)
+                    .ok()
+                    .and_then(|stored| self.expires(&stored))
+                    .map_or(false, |expires| expires > now);
+                if fresh {
+                    continue;
+                }
+            }
+            fs::remove_file(&path).map_err(|e| cache_error(&path, &e))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+// Write `value` as JSON to `tmp` and then move it into place at `path`, so
+// readers never see a partly written file. On Unix the file is only
+// readable and writable by its owner.
+pub(crate) fn write_atomic<T>(tmp: &Path, path: &Path, value: &T) -> io::Result<()>
+where
+    T: Serialize,
+{
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut writer = BufWriter::new(options.open(tmp)?);
+    serde_json::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    fs::rename(tmp, path)
+}
@@ -1,6 +1,7 @@
 use serde_json::Value;
 
-use {PassiveTotal, Result};
+use query::HOST_KINDS;
+use {PassiveTotal, Query, QueryKind, Result};
 
 const URL_DATA: &str = "/enrichment";
 const URL_OSINT: &str = "/enrichment/osint";
@@ -11,54 +12,54 @@ pub struct EnrichmentRequest<'a> {
     pt: &'a PassiveTotal,
 }
 
-request_struct!(EnrichmentData { query: String });
-request_struct!(EnrichmentOsint { query: String });
-request_struct!(EnrichmentMalware { query: String });
-request_struct!(EnrichmentSubdomains { query: String });
+request_struct!(EnrichmentData { query: Query });
+request_struct!(EnrichmentOsint { query: Query });
+request_struct!(EnrichmentMalware { query: Query });
+request_struct!(EnrichmentSubdomains { query: Query });
 
 impl<'a> EnrichmentRequest<'a> {
-    pub fn data<S>(self, query: S) -> EnrichmentData<'a>
+    pub fn data<S>(self, query: S) -> Result<EnrichmentData<'a>>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
-        EnrichmentData {
+        Ok(EnrichmentData {
             pt: self.pt,
             url: URL_DATA,
-            query: query.into(),
-        }
+            query: Query::parse_any(query.as_ref(), HOST_KINDS)?,
+        })
     }
 
-    pub fn osint<S>(self, query: S) -> EnrichmentOsint<'a>
+    pub fn osint<S>(self, query: S) -> Result<EnrichmentOsint<'a>>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
-        EnrichmentOsint {
+        Ok(EnrichmentOsint {
             pt: self.pt,
             url: URL_OSINT,
-            query: query.into(),
-        }
+            query: Query::parse_any(query.as_ref(), HOST_KINDS)?,
+        })
     }
 
-    pub fn malware<S>(self, query: S) -> EnrichmentMalware<'a>
+    pub fn malware<S>(self, query: S) -> Result<EnrichmentMalware<'a>>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
-        EnrichmentMalware {
+        Ok(EnrichmentMalware {
             pt: self.pt,
             url: URL_MALWARE,
-            query: query.into(),
-        }
+            query: Query::parse_any(query.as_ref(), HOST_KINDS)?,
+        })
     }
 
-    pub fn subdomains<S>(self, query: S) -> EnrichmentSubdomains<'a>
+    pub fn subdomains<S>(self, query: S) -> Result<EnrichmentSubdomains<'a>>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
-        EnrichmentSubdomains {
+        Ok(EnrichmentSubdomains {
             pt: self.pt,
             url: URL_SUBDOMAINS,
-            query: query.into(),
-        }
+            query: Query::parse_as(query.as_ref(), QueryKind::Domain)?,
+        })
     }
 }
 
@@ -4,6 +4,8 @@ use std::result;
 
 use reqwest;
 
+use QueryKind;
+
 /// `passivetotal-reqest` specific `Result` type.
 ///
 /// Returned by all queries to indicate a variety of possible errors from
@@ -15,6 +17,9 @@ pub type Result<T> = result::Result<T, PassiveTotalError>;
 pub enum PassiveTotalError {
     /// Returned when no valid domain can be parsed from a given query
     InvalidDomain,
+    /// Returned when a query is not valid for any of the listed `QueryKind`s
+    /// accepted by the request
+    InvalidQuery(String, Vec<QueryKind>),
     /// An error returned by `reqwest`.
     ReqwestError(reqwest::Error),
     /// Requests to the Passivetotal API that return a status code 400-499
@@ -30,12 +35,39 @@ pub enum PassiveTotalError {
     SslFieldParseError(String),
     /// An error when creating an `WhoisField` enum from a `&str`
     WhoisFieldParseError(String),
+    /// Returned when a configuration can not be read or is incomplete
+    ConfigError(String),
+    /// Returned when a `CredentialProvider` is unable to provide credentials
+    CredentialError(String),
+    /// Returned to a request that was coalesced with an identical request
+    /// already in flight when that request failed with an error that can
+    /// not be shared, such as a `ReqwestError`
+    CoalescedError(String),
+    /// Returned when a `DiskCache` can not be read or written
+    CacheError(String),
+    /// Returned in offline mode when the `DiskCache` has no response for a
+    /// request, with the endpoint and parameters of the request
+    OfflineCacheMiss(String),
+    /// Returned when a local archive, such as a `PdnsArchive` or
+    /// `WhoisStore`, can not be read or written
+    ArchiveError(String),
+    /// Returned when an `EventSink` is unable to emit a `Watcher` event
+    WatchError(String),
+    /// Returned by `Pages` when an item can not be parsed into the requested
+    /// type
+    ItemParseError(String),
+    /// An error when creating a `Pivot` enum from a `&str`
+    PivotParseError(String),
 }
 
 impl fmt::Display for PassiveTotalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             PassiveTotalError::InvalidDomain => write!(f, "Invalid domain given."),
+            PassiveTotalError::InvalidQuery(ref s, ref kinds) => {
+                let kinds: Vec<&str> = kinds.iter().map(QueryKind::as_str).collect();
+                write!(f, "Invalid query {}, expected: {}", s, kinds.join(", "))
+            },
             PassiveTotalError::ReqwestError(ref err) => write!(f, "Reqwest error: {}", err),
             PassiveTotalError::ClientError(ref code) => write!(f, "Client error: {}", code),
             PassiveTotalError::ServerError(ref code) => write!(f, "Server error: {}", code),
@@ -45,6 +77,21 @@ impl fmt::Display for PassiveTotalError {
             PassiveTotalError::WhoisFieldParseError(ref s) => {
                 write!(f, "Error parsing WhoisField from {}", s)
             },
+            PassiveTotalError::ConfigError(ref s) => write!(f, "Config error: {}", s),
+            PassiveTotalError::CredentialError(ref s) => write!(f, "Credential error: {}", s),
+            PassiveTotalError::CoalescedError(ref s) => {
+                write!(f, "Coalesced request error: {}", s)
+            },
+            PassiveTotalError::CacheError(ref s) => write!(f, "Cache error: {}", s),
+            PassiveTotalError::OfflineCacheMiss(ref s) => {
+                write!(f, "No cached response in offline mode for {}", s)
+            },
+            PassiveTotalError::ArchiveError(ref s) => write!(f, "Archive error: {}", s),
+            PassiveTotalError::WatchError(ref s) => write!(f, "Watch error: {}", s),
+            PassiveTotalError::ItemParseError(ref s) => write!(f, "Error parsing item: {}", s),
+            PassiveTotalError::PivotParseError(ref s) => {
+                write!(f, "Error parsing Pivot from {}", s)
+            },
         }
     }
 }
@@ -53,6 +100,9 @@ impl error::Error for PassiveTotalError {
     fn description(&self) -> &str {
         match *self {
             PassiveTotalError::InvalidDomain => "Invalid domain given.",
+            PassiveTotalError::InvalidQuery(..) => {
+                "Invalid query. The query is not valid for the requested endpoint."
+            },
             PassiveTotalError::ReqwestError(ref err) => error::Error::description(err),
             PassiveTotalError::ClientError(_) => {
                 "Client error. This is usually caused by a malformed request."
@@ -66,6 +116,31 @@ impl error::Error for PassiveTotalError {
             PassiveTotalError::WhoisFieldParseError(_) => {
                 "WhoisFieldParse error. Unable to parse given string into an WhoisField."
             },
+            PassiveTotalError::ConfigError(_) => {
+                "Config error. Unable to read the configuration or it is missing values."
+            },
+            PassiveTotalError::CredentialError(_) => {
+                "Credential error. Unable to get credentials from the credential provider."
+            },
+            PassiveTotalError::CoalescedError(_) => {
+                "Coalesced request error. An identical request in flight at the same time failed."
+            },
+            PassiveTotalError::CacheError(_) => {
+                "Cache error. Unable to read or write the response cache on disk."
+            },
+            PassiveTotalError::OfflineCacheMiss(_) => {
+                "Offline cache miss. The request has no cached response and offline mode is on."
+            },
+            PassiveTotalError::ArchiveError(_) => {
+                "Archive error. Unable to read or write a local archive."
+            },
+            PassiveTotalError::WatchError(_) => "Watch error. Unable to emit a watch event.",
+            PassiveTotalError::ItemParseError(_) => {
+                "ItemParse error. Unable to parse an item of a paged response."
+            },
+            PassiveTotalError::PivotParseError(_) => {
+                "PivotParse error. Unable to parse given string into a Pivot."
+            },
         }
     }
 
@@ -0,0 +1,197 @@
+use regex::Regex;
+use serde_json::Value;
+
+use query::{QueryKind, HOST_KINDS};
+use whois::WhoisField;
+use {utils, PassiveTotal, PassiveTotalError, Query, Result};
+
+lazy_static! {
+    // Patterns are applied in this order. Each indicator found is blanked
+    // out of the text before the next pattern is applied so that, for
+    // example, the domain of a URL or email address is not also reported on
+    // its own.
+    // Defanged schemes are matched here as `refang` only refangs a scheme at
+    // the start of its input, not in the middle of free text
+    static ref URL: Regex = Regex::new(
+        r#"(?i)\b(?:h(?:tt|xx|\*\*)ps?|ftp|fxp)://[^\s<>"'`]+"#
+    ).unwrap();
+    static ref EMAIL: Regex = Regex::new(r"[\w.%+-]+@(?:[\w-]+\.)+[\w-]{2,}").unwrap();
+    static ref FINGERPRINT: Regex = Regex::new(
+        r"\b[[:xdigit:]]{2}(?::[[:xdigit:]]{2}){19}\b"
+    ).unwrap();
+    static ref HASH: Regex = Regex::new(
+        r"\b(?:[[:xdigit:]]{64}|[[:xdigit:]]{40}|[[:xdigit:]]{32})\b"
+    ).unwrap();
+    static ref IPV6: Regex = Regex::new(
+        r"(?i)\[?(?:[0-9a-f]{0,4}:){2,7}(?:(?:\d{1,3}\.){3}\d{1,3}|[0-9a-f]{1,4})?\]?"
+    ).unwrap();
+    static ref IPV4: Regex = Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap();
+    static ref DOMAIN: Regex = Regex::new(r"\b(?:[\w-]{1,63}\.)+[\w-]{2,63}\b").unwrap();
+}
+
+// IPv4-mapped IPv6 addresses parse as `Ipv4`
+const IPV6_KINDS: &[QueryKind] = &[QueryKind::Ipv6, QueryKind::Ipv4];
+
+// Query kinds that `PassiveTotal::lookup` can look up
+const LOOKUP_KINDS: &[QueryKind] = &[
+    QueryKind::Domain,
+    QueryKind::Ipv4,
+    QueryKind::Ipv6,
+    QueryKind::Email,
+    QueryKind::Certificate,
+];
+
+/// An indicator extracted from free text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Indicator {
+    /// The indicator parsed into a `Query`
+    pub query: Query,
+    /// The refanged URL the indicator was extracted from, if it was found as
+    /// the host of a URL
+    pub url: Option<String>,
+}
+
+// Check if a character joins onto an indicator, meaning a match next to it
+// is only part of a longer word
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Find all matches of `re` in `text` that are not part of a longer word and
+// `parse` into an indicator, blank them out and return the indicators.
+// Matches that do not parse, such as a timestamp matching the IPv6 pattern,
+// are left in the text for the later patterns.
+fn take_matches<T, F>(re: &Regex, text: &mut String, mut parse: F) -> Vec<T>
+where
+    F: FnMut(&str) -> Option<T>,
+{
+    let mut found = Vec::new();
+    let mut blanked = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for m in re.find_iter(text) {
+        let (start, end) = (m.start(), m.end());
+        if text[..start].chars().next_back().map_or(false, is_word_char) ||
+            text[end..].chars().next().map_or(false, is_word_char)
+        {
+            continue;
+        }
+        if let Some(indicator) = parse(m.as_str()) {
+            found.push(indicator);
+            blanked.push_str(&text[last..start]);
+            blanked.push_str(&" ".repeat(end - start));
+            last = end;
+        }
+    }
+
+    blanked.push_str(&text[last..]);
+    *text = blanked;
+    found
+}
+
+// Strip trailing punctuation that is part of the surrounding sentence rather
+// than the URL
+fn trim_url(url: &str) -> &str {
+    url.trim_right_matches(|c| ".,;:!?)]}".contains(c))
+}
+
+/// Extract indicators from free text such as incident reports and emails.
+///
+/// Finds URLs, email addresses, certificate fingerprints, MD5, SHA-1 and
+/// SHA-256 hashes, IPv4 and IPv6 addresses and domains. The text is refanged
+/// first, so defanged indicators such as `evil[.]com` are found. URLs are
+/// reported as the `Query` for their host.
+///
+/// Colon separated SHA-1 fingerprints are classified as
+/// `Query::Certificate` while bare 40 character hex strings are classified
+/// as `Query::Hash`. Domains are only reported if they end in a suffix from
+/// the Public Suffix List, so file names such as `invoice.exe` are skipped.
+///
+/// Indicators are returned in the order they were found, with duplicates
+/// removed.
+pub fn extract_indicators(text: &str) -> Vec<Indicator> {
+    let mut text = utils::refang(text);
+    let mut indicators: Vec<Indicator> = Vec::new();
+
+    {
+        let mut push = |query: Query, url: Option<String>| {
+            if !indicators.iter().any(|i| i.query == query) {
+                indicators.push(Indicator { query, url });
+            }
+        };
+
+        let urls = take_matches(&URL, &mut text, |url| {
+            let url = utils::refang(trim_url(url));
+            Query::parse_any(&url, HOST_KINDS).ok().map(|query| (query, url))
+        });
+        for (query, url) in urls {
+            push(query, Some(url));
+        }
+
+        let patterns: [(&Regex, QueryKind); 6] = [
+            (&EMAIL, QueryKind::Email),
+            (&FINGERPRINT, QueryKind::Certificate),
+            (&HASH, QueryKind::Hash),
+            (&IPV6, QueryKind::Ipv6),
+            (&IPV4, QueryKind::Ipv4),
+            (&DOMAIN, QueryKind::Domain),
+        ];
+        for &(re, kind) in &patterns {
+            let queries = take_matches(re, &mut text, |m| {
+                let query = match kind {
+                    QueryKind::Ipv6 => Query::parse_any(m, IPV6_KINDS),
+                    _ => Query::parse_as(m, kind),
+                };
+                match query {
+                    Ok(Query::Domain(ref domain)) if !utils::has_known_suffix(domain) => None,
+                    Ok(query) => Some(query),
+                    Err(_) => None,
+                }
+            });
+            for query in queries {
+                push(query, None);
+            }
+        }
+    }
+
+    indicators
+}
+
+/// Extract the deduplicated `Query`s from free text.
+///
+/// See `extract_indicators` for details.
+pub fn extract_queries(text: &str) -> Vec<Query> {
+    extract_indicators(text).into_iter().map(|i| i.query).collect()
+}
+
+impl PassiveTotal {
+    /// Look up a `Query` using the endpoint that best fits its kind.
+    ///
+    /// Domains and IP addresses are looked up with `passive_dns`, email
+    /// addresses with a WHOIS email search and certificates with
+    /// `ssl().certificate()`. Passivetotal has no endpoint for file hashes
+    /// so these return an `InvalidQuery` error.
+    pub fn lookup(&self, query: &Query) -> Result<Value> {
+        let q = query.to_string();
+        match *query {
+            Query::Domain(_) | Query::Ipv4(_) | Query::Ipv6(_) => self.passive_dns(&q)?.send(),
+            Query::Email(_) => self.whois().search_field(&q, WhoisField::Email)?.send(),
+            Query::Certificate(_) => self.ssl().certificate(&q)?.send(),
+            Query::Hash(_) => Err(PassiveTotalError::InvalidQuery(q, LOOKUP_KINDS.to_vec())),
+        }
+    }
+
+    /// Look up each of the given queries in turn with `lookup`.
+    ///
+    /// A failed lookup does not stop the remaining queries from being looked
+    /// up. Results are returned in the same order as the queries.
+    pub fn lookup_all<'q, I>(&self, queries: I) -> Vec<(Query, Result<Value>)>
+    where
+        I: IntoIterator<Item = &'q Query>,
+    {
+        queries
+            .into_iter()
+            .map(|q| (q.clone(), self.lookup(q)))
+            .collect()
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::{Serialize, Serializer};
+use serde_json::{self, Map, Value};
+
+use pivot::{collect_emails, parse_all, result_fields, subdomains, Expansion, Pivot};
+use query::{HOST_KINDS, IP_KINDS};
+use {Query, QueryKind};
+
+const GRAPHML_NS: &str = "http://graphml.graphdrawing.org/xmlns";
+
+/// The kinds of node in a `Graph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NodeKind {
+    Domain,
+    Ip,
+    Email,
+    Certificate,
+    Asn,
+}
+
+/// The relationships between nodes in a `Graph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// A domain resolved to an IP or another domain
+    ResolvesTo,
+    /// A domain was registered with an email address
+    RegisteredBy,
+    /// An IP served an SSL certificate
+    ServesCertificate,
+    /// A domain is a subdomain of another domain
+    SubdomainOf,
+    /// An IP is announced by an ASN
+    AnnouncedBy,
+}
+
+/// A node in a `Graph`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// Identifies the node in its graph, made up of its kind and value
+    pub id: String,
+    pub kind: NodeKind,
+    /// The indicator, such as the domain or IP address
+    pub value: String,
+    /// Extra details about the node, such as the pivot that found it
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A directed edge between two nodes in a `Graph`, by their ids
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+}
+
+/// A graph of infrastructure, built from API responses or a pivot
+/// `Expansion`, that can be exported for graph tools.
+///
+/// Nodes and edges are only added once, and are exported sorted by their
+/// ids so the same graph is always written the same way.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{Graph, PassiveTotal, Query};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let query = Query::parse("passivetotal.org").unwrap();
+/// let pdns = pt.passive_dns("passivetotal.org").unwrap().send().unwrap();
+///
+/// let mut graph = Graph::new();
+/// graph.add_passive_dns(&query, &pdns);
+/// graph.write_dot(&mut std::io::stdout()).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Graph {
+    nodes: BTreeMap<String, Node>,
+    edges: BTreeSet<Edge>,
+}
+
+impl NodeKind {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            NodeKind::Domain => "domain",
+            NodeKind::Ip => "ip",
+            NodeKind::Email => "email",
+            NodeKind::Certificate => "certificate",
+            NodeKind::Asn => "asn",
+        }
+    }
+
+    /// Returns the kind of node for a query, if it has one. Hashes do not.
+    pub fn of(query: &Query) -> Option<NodeKind> {
+        match query.kind() {
+            QueryKind::Domain => Some(NodeKind::Domain),
+            QueryKind::Ipv4 | QueryKind::Ipv6 => Some(NodeKind::Ip),
+            QueryKind::Email => Some(NodeKind::Email),
+            QueryKind::Certificate => Some(NodeKind::Certificate),
+            QueryKind::Hash => None,
+        }
+    }
+
+    // Graphviz shape used for the kind
+    fn shape(&self) -> &str {
+        match *self {
+            NodeKind::Domain => "ellipse",
+            NodeKind::Ip => "box",
+            NodeKind::Email => "note",
+            NodeKind::Certificate => "component",
+            NodeKind::Asn => "hexagon",
+        }
+    }
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            EdgeKind::ResolvesTo => "resolves-to",
+            EdgeKind::RegisteredBy => "registered-by",
+            EdgeKind::ServesCertificate => "serves-certificate",
+            EdgeKind::SubdomainOf => "subdomain-of",
+            EdgeKind::AnnouncedBy => "announced-by",
+        }
+    }
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for NodeKind {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for EdgeKind {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+// Escape text for an XML attribute or element
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Quote text as a Graphviz ID
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Returns an ASN as `AS` followed by its number, from a number or a string
+// with or without the `AS` prefix
+fn asn(value: &Value) -> Option<String> {
+    let number = match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => {
+            let s = s.trim();
+            let digits = match s.get(..2) {
+                Some(prefix) if prefix.eq_ignore_ascii_case("as") => &s[2..],
+                _ => s,
+            };
+            digits.parse().ok()
+        },
+        _ => None,
+    };
+    number.map(|n: u64| format!("AS{}", n))
+}
+
+impl Graph {
+    /// Create an empty graph
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+
+    /// Create a graph of the nodes and edges found by a pivot expansion.
+    ///
+    /// Each node records the `depth` it was found at and, apart from the
+    /// seed, the `pivot` that found it and the node it was found `from`.
+    pub fn from_expansion(expansion: &Expansion) -> Graph {
+        let mut graph = Graph::new();
+
+        for node in &expansion.nodes {
+            if let Some(id) = graph.add_query(&node.query) {
+                let attributes = &mut graph.nodes.get_mut(&id).unwrap().attributes;
+                attributes.insert(String::from("depth"), node.depth.to_string());
+                if let Some(pivot) = node.pivot {
+                    attributes.insert(String::from("pivot"), pivot.to_string());
+                }
+                if let Some(ref from) = node.from {
+                    attributes.insert(String::from("from"), from.to_string());
+                }
+            }
+        }
+
+        for edge in &expansion.edges {
+            let (from, to, kind) = match edge.pivot {
+                // Edges point from the domain in a resolution
+                Pivot::PassiveDns if edge.from.kind() != QueryKind::Domain => {
+                    (&edge.to, &edge.from, EdgeKind::ResolvesTo)
+                },
+                Pivot::PassiveDns => (&edge.from, &edge.to, EdgeKind::ResolvesTo),
+                Pivot::WhoisEmail => (&edge.from, &edge.to, EdgeKind::RegisteredBy),
+                Pivot::WhoisDomains => (&edge.to, &edge.from, EdgeKind::RegisteredBy),
+                Pivot::SslCertificates => (&edge.from, &edge.to, EdgeKind::ServesCertificate),
+                Pivot::SslIps => (&edge.to, &edge.from, EdgeKind::ServesCertificate),
+                Pivot::Subdomains => (&edge.to, &edge.from, EdgeKind::SubdomainOf),
+            };
+            graph.link(from, to, kind);
+        }
+
+        graph
+    }
+
+    /// Add a node if it is not already in the graph, returning its id
+    pub fn add_node<S>(&mut self, kind: NodeKind, value: S) -> String
+    where
+        S: Into<String>,
+    {
+        let value = value.into();
+        let id = format!("{}:{}", kind, value);
+        self.nodes.entry(id.clone()).or_insert_with(|| Node {
+            id: id.clone(),
+            kind,
+            value,
+            attributes: BTreeMap::new(),
+        });
+        id
+    }
+
+    /// Add a node for a query, returning its id, or `None` if the query is a
+    /// kind without a node such as a hash
+    pub fn add_query(&mut self, query: &Query) -> Option<String> {
+        NodeKind::of(query).map(|kind| self.add_node(kind, query.to_string()))
+    }
+
+    /// Add an edge between two nodes by their ids, which must already be in
+    /// the graph. Returns `false` if either node is missing.
+    pub fn add_edge(&mut self, source: &str, target: &str, kind: EdgeKind) -> bool {
+        if !self.nodes.contains_key(source) || !self.nodes.contains_key(target) {
+            return false;
+        }
+        self.edges.insert(Edge {
+            source: String::from(source),
+            target: String::from(target),
+            kind,
+        });
+        true
+    }
+
+    // Add the nodes for two queries and the edge between them
+    fn link(&mut self, from: &Query, to: &Query, kind: EdgeKind) {
+        if let (Some(from), Some(to)) = (self.add_query(from), self.add_query(to)) {
+            self.add_edge(&from, &to, kind);
+        }
+    }
+
+    /// Add the resolutions in a passive DNS response for `query`
+    pub fn add_passive_dns(&mut self, query: &Query, resp: &Value) {
+        for resolve in parse_all(result_fields(resp, "resolve"), HOST_KINDS) {
+            match query.kind() {
+                QueryKind::Domain => self.link(query, &resolve, EdgeKind::ResolvesTo),
+                _ => self.link(&resolve, query, EdgeKind::ResolvesTo),
+            }
+        }
+    }
+
+    /// Add the email addresses in the WHOIS record of `domain`
+    pub fn add_whois(&mut self, domain: &Query, resp: &Value) {
+        let mut emails = Vec::new();
+        collect_emails(resp, &mut emails);
+        for email in parse_all(emails, &[QueryKind::Email]) {
+            self.link(domain, &email, EdgeKind::RegisteredBy);
+        }
+    }
+
+    /// Add the certificates served in an SSL certificate history response
+    /// for `query`, which is either an IP or a certificate
+    pub fn add_ssl_history(&mut self, query: &Query, resp: &Value) {
+        if query.kind() == QueryKind::Certificate {
+            for ip in parse_all(result_fields(resp, "ipAddresses"), IP_KINDS) {
+                self.link(&ip, query, EdgeKind::ServesCertificate);
+            }
+        } else {
+            for cert in parse_all(result_fields(resp, "sha1"), &[QueryKind::Certificate]) {
+                self.link(query, &cert, EdgeKind::ServesCertificate);
+            }
+        }
+    }
+
+    /// Add the ASN in an enrichment data response for the IP `query`
+    pub fn add_enrichment(&mut self, query: &Query, resp: &Value) {
+        let asn = match resp.get("autonomousSystemNumber").and_then(asn) {
+            Some(asn) => asn,
+            None => return,
+        };
+        let ip = match self.add_query(query) {
+            Some(ip) => ip,
+            None => return,
+        };
+
+        let id = self.add_node(NodeKind::Asn, asn);
+        if let Some(name) = resp.get("autonomousSystemName").and_then(Value::as_str) {
+            let attributes = &mut self.nodes.get_mut(&id).unwrap().attributes;
+            attributes.insert(String::from("name"), String::from(name));
+        }
+        self.add_edge(&ip, &id, EdgeKind::AnnouncedBy);
+    }
+
+    /// Add the subdomains in an enrichment subdomains response for `domain`
+    pub fn add_subdomains(&mut self, domain: &Query, resp: &Value) {
+        let (domain, subdomains) = subdomains(resp, domain);
+        for sub in subdomains {
+            self.link(&sub, &domain, EdgeKind::SubdomainOf);
+        }
+    }
+
+    /// Returns the node with the given id
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    /// Returns every node, sorted by id
+    pub fn nodes(&self) -> Vec<&Node> {
+        self.nodes.values().collect()
+    }
+
+    /// Returns every edge, sorted by source, target and kind
+    pub fn edges(&self) -> Vec<&Edge> {
+        self.edges.iter().collect()
+    }
+
+    // The names of every node attribute used in the graph
+    fn attribute_names(&self) -> BTreeSet<&str> {
+        self.nodes
+            .values()
+            .flat_map(|n| n.attributes.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Write the graph as GraphML
+    pub fn write_graphml<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let attributes = self.attribute_names();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<graphml xmlns="{}">"#, GRAPHML_NS)?;
+        writeln!(out, r#"  <key id="kind" for="node" attr.name="kind" attr.type="string"/>"#)?;
+        writeln!(out, r#"  <key id="value" for="node" attr.name="value" attr.type="string"/>"#)?;
+        for name in &attributes {
+            let name = xml_escape(name);
+            writeln!(
+                out,
+                r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="string"/>"#,
+                name
+            )?;
+        }
+        writeln!(
+            out,
+            r#"  <key id="relation" for="edge" attr.name="relation" attr.type="string"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="passivetotal" edgedefault="directed">"#)?;
+
+        for node in self.nodes.values() {
+            writeln!(out, r#"    <node id="{}">"#, xml_escape(&node.id))?;
+            writeln!(out, r#"      <data key="kind">{}</data>"#, node.kind)?;
+            writeln!(out, r#"      <data key="value">{}</data>"#, xml_escape(&node.value))?;
+            for (name, value) in &node.attributes {
+                writeln!(
+                    out,
+                    r#"      <data key="{}">{}</data>"#,
+                    xml_escape(name),
+                    xml_escape(value)
+                )?;
+            }
+            writeln!(out, "    </node>")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                r#"    <edge source="{}" target="{}">"#,
+                xml_escape(&edge.source),
+                xml_escape(&edge.target)
+            )?;
+            writeln!(out, r#"      <data key="relation">{}</data>"#, edge.kind)?;
+            writeln!(out, "    </edge>")?;
+        }
+
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    /// Write the graph in the Graphviz DOT language, with the value of each
+    /// node as its label and a shape for each kind of node
+    pub fn write_dot<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        writeln!(out, "digraph passivetotal {{")?;
+        for node in self.nodes.values() {
+            write!(
+                out,
+                "  {} [label={}, kind={}, shape={}",
+                dot_quote(&node.id),
+                dot_quote(&node.value),
+                dot_quote(node.kind.as_str()),
+                node.kind.shape()
+            )?;
+            for (name, value) in &node.attributes {
+                write!(out, ", {}={}", dot_quote(name), dot_quote(value))?;
+            }
+            writeln!(out, "];")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "  {} -> {} [label={}];",
+                dot_quote(&edge.source),
+                dot_quote(&edge.target),
+                dot_quote(edge.kind.as_str())
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Returns the graph in JSON Graph Format, version 2
+    pub fn to_json_graph(&self) -> Value {
+        let mut nodes = Map::new();
+        for node in self.nodes.values() {
+            let mut metadata = Map::new();
+            metadata.insert(String::from("kind"), json!(node.kind));
+            for (name, value) in &node.attributes {
+                metadata.insert(name.clone(), json!(value));
+            }
+            nodes.insert(
+                node.id.clone(),
+                json!({ "label": node.value, "metadata": metadata }),
+            );
+        }
+
+        let edges: Vec<Value> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "source": edge.source,
+                    "target": edge.target,
+                    "relation": edge.kind,
+                    "directed": true,
+                })
+            })
+            .collect();
+
+        json!({
+            "graph": {
+                "directed": true,
+                "nodes": nodes,
+                "edges": edges,
+            }
+        })
+    }
+
+    /// Write the graph in JSON Graph Format, version 2
+    pub fn write_json_graph<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        serde_json::to_writer_pretty(&mut *out, &self.to_json_graph())?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_xml() {
+        assert_eq!(xml_escape("evil.com"), "evil.com");
+        assert_eq!(
+            xml_escape(r#"<a href="x">Tom & 'Jerry' \ Co</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos; \\ Co&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn quote_dot() {
+        assert_eq!(dot_quote("evil.com"), r#""evil.com""#);
+        assert_eq!(dot_quote(r#"say "hi" & C:\dir\"#), r#""say \"hi\" & C:\\dir\\""#);
+    }
+
+    #[test]
+    fn asn_formats() {
+        assert_eq!(asn(&json!(15169)), Some(String::from("AS15169")));
+        assert_eq!(asn(&json!("15169")), Some(String::from("AS15169")));
+        assert_eq!(asn(&json!(" as15169 ")), Some(String::from("AS15169")));
+        assert_eq!(asn(&json!("AS15169")), Some(String::from("AS15169")));
+        assert_eq!(asn(&json!("AS")), None);
+        assert_eq!(asn(&json!("Google")), None);
+        assert_eq!(asn(&json!(null)), None);
+        // A multi-byte first character must not split the prefix check
+        assert_eq!(asn(&json!("é15169")), None);
+        assert_eq!(asn(&json!("Ä")), None);
+    }
+}
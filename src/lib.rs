@@ -18,20 +18,24 @@
 //! Then add it to your crate:
 //!
 //! ```
-//! extern crate passivetotal_reqwest
+//! extern crate passivetotal_reqwest;
 //! ```
 //!
 //! All methods that make Passivetotal API requests return a
 //! `Result<serde_json::Value>` to allow access to the JSON object returned
 //! in Passivetotal API responses.
 //!
+//! Requests that take an indicator validate it when the request is created
+//! and return a `PassiveTotalError::InvalidDomain` or
+//! `PassiveTotalError::InvalidQuery` if it is not valid for that endpoint.
+//!
 //! # Example
 //!
 //! ```no_run
 //! use passivetotal_reqwest::PassiveTotal;
 //!
 //! let pt = PassiveTotal::with_auth("username", "apikey");
-//! let resp = pt.passive_dns("www.passivetotal.org").unwrap();
+//! let resp = pt.passive_dns("www.passivetotal.org").unwrap().send().unwrap();
 //!
 //! println!("{}", resp)
 //! ```
@@ -45,40 +49,105 @@
 //! [3]: https://www.passivetotal.org
 //! [4]: https://api.passivetotal.org/api/docs/
 extern crate chrono;
+extern crate dirs;
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
 extern crate reqwest;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate toml;
 extern crate url;
 
+use std::sync::RwLock;
 use std::time::Duration;
 
 use serde_json::Value;
 
+use coalesce::Coalescer;
+use ratelimit::RateLimiter;
+
 #[macro_use]
 mod macros;
 mod account;
 mod actions;
+mod archive;
+mod articles;
+mod batch;
+mod cache;
+mod coalesce;
+mod config;
+mod credentials;
+mod diskcache;
 mod enrichment;
 mod error;
+mod extract;
+mod graph;
+mod monitor;
+mod pages;
 mod ssl;
 mod passive;
+mod pivot;
+mod projects;
+mod query;
+mod ratelimit;
+mod secret;
+mod teamstream;
 mod utils;
+mod watch;
 mod whois;
+mod whoisdiff;
 
+pub use archive::{PdnsArchive, PdnsRecord};
+pub use batch::{Batch, BatchIter, Request};
+pub use cache::{CacheStats, EndpointFamily, ResponseCache};
+pub use config::Config;
+pub use credentials::{
+    CommandCredentials, CredentialProvider, Credentials, EnvCredentials, FileCredentials,
+    StaticCredentials,
+};
+pub use diskcache::{DiskCache, DiskCacheEntry};
 pub use error::{PassiveTotalError, Result};
+pub use extract::{extract_indicators, extract_queries, Indicator};
+pub use graph::{Edge, EdgeKind, Graph, Node, NodeKind};
+pub use pages::{Paged, Pages};
+pub use pivot::{Expansion, Pivot, PivotEdge, PivotError, PivotNode, Pivoter, ALL_PIVOTS};
+pub use query::{Query, QueryKind};
+pub use secret::Secret;
 pub use ssl::SslField;
+pub use teamstream::{TeamstreamEvent, TeamstreamFollower};
+pub use utils::{
+    defang, domain_parts, domain_to_unicode, refang, registrable_domain, suspicious_labels,
+    DomainParts,
+};
+pub use watch::{EventSink, WatchChange, WatchEvent, Watcher, WebhookSink, WriterSink};
 pub use whois::WhoisField;
+pub use whoisdiff::{diff_whois, ChangeKind, FieldChange, WhoisSnapshot, WhoisStore};
 
 const BASE_URL: &str = "https://api.passivetotal.org/v2";
 
 /// Struct used to access the Passivetotal v2 API.
+///
+/// Credentials are fetched from a `CredentialProvider` and stored as a
+/// `Secret` so the API key is redacted from `Debug` output.
+///
+/// Identical requests made at the same time, for example by several threads
+/// looking up the same indicator, are coalesced so that only one is sent to
+/// the API and all of them receive its result.
 #[derive(Debug)]
 pub struct PassiveTotal {
-    username: String,
-    apikey: String,
+    provider: Box<CredentialProvider>,
+    credentials: RwLock<Option<Credentials>>,
     timeout: Duration,
+    base_url: String,
+    rate_limit: Option<RateLimiter>,
+    inflight: Coalescer,
+    cache: Option<ResponseCache>,
+    disk_cache: Option<DiskCache>,
+    offline: bool,
 }
 
 impl PassiveTotal {
@@ -86,11 +155,30 @@ impl PassiveTotal {
     pub fn new<S>(username: S, apikey: S, timeout: Duration) -> Self
     where
         S: Into<String>,
+    {
+        PassiveTotal::with_credential_provider(StaticCredentials::new(username, apikey), timeout)
+    }
+
+    /// Create a new PassiveTotal struct that gets its credentials from the
+    /// given `CredentialProvider`.
+    ///
+    /// Credentials are fetched when the first request is made and fetched
+    /// again if a request fails with a 401, in which case the request is
+    /// retried once if the credentials have changed.
+    pub fn with_credential_provider<P>(provider: P, timeout: Duration) -> Self
+    where
+        P: CredentialProvider + 'static,
     {
         PassiveTotal {
-            username: username.into(),
-            apikey: apikey.into(),
+            provider: Box::new(provider),
+            credentials: RwLock::new(None),
             timeout,
+            base_url: String::from(BASE_URL),
+            rate_limit: None,
+            inflight: Coalescer::default(),
+            cache: None,
+            disk_cache: None,
+            offline: false,
         }
     }
 
@@ -102,25 +190,182 @@ impl PassiveTotal {
     where
         S: Into<String>,
     {
-        PassiveTotal {
-            username: username.into(),
-            apikey: apikey.into(),
-            timeout: Duration::from_secs(60),
+        PassiveTotal::new(username, apikey, Duration::from_secs(60))
+    }
+
+    /// Use a different base URL for the Passivetotal API, for example to go
+    /// through a proxy. Defaults to `https://api.passivetotal.org/v2`.
+    pub fn with_base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into().trim_right_matches('/').to_owned();
+        self
+    }
+
+    /// Limit the number of requests started per minute. Requests over the
+    /// limit block until they are allowed to start.
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limit = Some(RateLimiter::per_minute(requests_per_minute));
+        self
+    }
+
+    /// Cache successful responses in the given `ResponseCache`.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the `ResponseCache` set with `with_cache`, for example to
+    /// check its `stats`.
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    /// Cache successful responses on disk in the given `DiskCache`, so they
+    /// can be reused by later runs. Used together with `with_cache` the
+    /// in-memory cache is checked first.
+    pub fn with_disk_cache(mut self, cache: DiskCache) -> Self {
+        self.disk_cache = Some(cache);
+        self
+    }
+
+    /// Returns the `DiskCache` set with `with_disk_cache`
+    pub fn disk_cache(&self) -> Option<&DiskCache> {
+        self.disk_cache.as_ref()
+    }
+
+    /// Answer every request from the `DiskCache` set with `with_disk_cache`
+    /// without using the network, for example on a host with a copy of
+    /// another host's cache.
+    ///
+    /// Cached responses are used even if they have expired. Only responses
+    /// stored with the same username and base URL are used. Requests without
+    /// a cached response return a `PassiveTotalError::OfflineCacheMiss`, as
+    /// do all requests if there is no `DiskCache`.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    // Returns the current credentials, fetching them from the provider if
+    // they have not been fetched yet
+    fn credentials(&self) -> Result<Credentials> {
+        if let Some(ref creds) = *self.credentials.read().unwrap_or_else(|e| e.into_inner()) {
+            return Ok(creds.clone());
         }
+
+        self.refresh_credentials()
     }
 
-    fn send_request_json_response<T>(&self, endpoint: &str, params: T) -> Result<Value>
+    // Fetch fresh credentials from the provider
+    fn refresh_credentials(&self) -> Result<Credentials> {
+        let creds = self.provider.credentials()?;
+        *self.credentials.write().unwrap_or_else(|e| e.into_inner()) = Some(creds.clone());
+        Ok(creds)
+    }
+
+    // The account responses are stored for in the `DiskCache`, so they are
+    // never shared between users or APIs
+    fn cache_account(&self) -> Result<String> {
+        Ok(format!("{} {}", self.credentials()?.username, self.base_url))
+    }
+
+    fn send_request<T>(
+        &self,
+        url: &str,
+        creds: &Credentials,
+        params: &T,
+    ) -> Result<reqwest::Response>
     where
         T: serde::ser::Serialize,
     {
-        let url = format!("{}{}", BASE_URL, endpoint);
-        let mut resp = reqwest::Client::builder()
+        if let Some(ref limiter) = self.rate_limit {
+            limiter.wait();
+        }
+
+        reqwest::Client::builder()
             .timeout(self.timeout)
             .build()?
-            .get(&url)
-            .basic_auth(self.username.as_str(), Some(self.apikey.as_str()))
-            .json(&params)
-            .send()?;
+            .get(url)
+            .basic_auth(creds.username.as_str(), Some(creds.apikey.expose()))
+            .json(params)
+            .send()
+            .map_err(From::from)
+    }
+
+    fn send_request_json_response<T>(&self, endpoint: &str, params: T) -> Result<Value>
+    where
+        T: serde::ser::Serialize,
+    {
+        // Requests are identical if they have the same endpoint and
+        // parameters. Parameters that fail to serialize will fail to send
+        // as well, so there is nothing to cache or coalesce.
+        let body = match serde_json::to_string(&params) {
+            Ok(body) => body,
+            Err(_) if self.offline => {
+                return Err(PassiveTotalError::OfflineCacheMiss(String::from(endpoint)))
+            },
+            Err(_) => return self.fetch_json(endpoint, &params),
+        };
+
+        if self.offline {
+            let account = self.cache_account()?;
+            let cached = self
+                .disk_cache
+                .as_ref()
+                .and_then(|c| c.get_stale(&account, endpoint, &body));
+            return cached.ok_or_else(|| {
+                PassiveTotalError::OfflineCacheMiss(format!("{} {}", endpoint, body))
+            });
+        }
+
+        let key = format!("{} {}", endpoint, body);
+
+        if let Some(value) = self.cache.as_ref().and_then(|c| c.get(endpoint, &key)) {
+            return Ok(value);
+        }
+
+        self.inflight.run(key.clone(), || {
+            let value = match self.disk_cache {
+                Some(ref cache) => {
+                    let account = self.cache_account()?;
+                    match cache.lookup(&account, endpoint, &body) {
+                        Some(value) => value,
+                        None => {
+                            let value = self.fetch_json(endpoint, &params)?;
+                            // The response is still usable if it can not be
+                            // stored, the next request will just miss
+                            let _ = cache.insert(&account, endpoint, &body, &value);
+                            value
+                        },
+                    }
+                },
+                None => self.fetch_json(endpoint, &params)?,
+            };
+            if let Some(ref cache) = self.cache {
+                cache.insert(endpoint, key, &value);
+            }
+            Ok(value)
+        })
+    }
+
+    fn fetch_json<T>(&self, endpoint: &str, params: &T) -> Result<Value>
+    where
+        T: serde::ser::Serialize,
+    {
+        let url = format!("{}{}", self.base_url, endpoint);
+        let creds = self.credentials()?;
+        let mut resp = self.send_request(&url, &creds, params)?;
+
+        // The key may have been rotated, retry once if the provider now has
+        // different credentials
+        if resp.status().as_u16() == 401 {
+            let fresh = self.refresh_credentials()?;
+            if !fresh.same_as(&creds) {
+                resp = self.send_request(&url, &fresh, params)?;
+            }
+        }
 
         if resp.status().is_client_error() {
             Err(PassiveTotalError::ClientError(resp.status()))
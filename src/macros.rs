@@ -7,6 +7,12 @@ macro_rules! impl_send {
                 self.pt.send_request_json_response(self.url, &self)
             }
         }
+
+        impl<'a> $crate::Request for $id<'a> {
+            fn send(&self) -> Result<Value> {
+                $id::send(self)
+            }
+        }
     };
 }
 
@@ -32,3 +38,25 @@ macro_rules! request_struct {
         }
     };
 }
+
+// Implements `Paged` for a request with a `page: Option<usize>` field, with
+// the items of each page in the given key of the response
+macro_rules! impl_paged {
+    ($id: ident) => {
+        impl_paged!($id, "results");
+    };
+    ($id: ident, $items: expr) => {
+        impl<'a> $crate::Paged for $id<'a> {
+            fn set_page(&mut self, page: usize) {
+                self.page = Some(page);
+            }
+
+            fn page_items(&self, resp: &::serde_json::Value) -> Vec<::serde_json::Value> {
+                match resp.get($items) {
+                    Some(::serde_json::Value::Array(items)) => items.clone(),
+                    _ => Vec::new(),
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use {utils, PassiveTotal, Result};
+
+const URL_MONITOR: &str = "/monitor";
+
+pub struct MonitorRequest<'a> {
+    pt: &'a PassiveTotal,
+}
+
+request_struct!(MonitorAlerts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifact: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+});
+
+impl<'a> MonitorRequest<'a> {
+    pub fn alerts(self) -> MonitorAlerts<'a> {
+        MonitorAlerts {
+            pt: self.pt,
+            url: URL_MONITOR,
+            project: None,
+            artifact: None,
+            start: None,
+            end: None,
+            size: None,
+            page: None,
+        }
+    }
+}
+
+impl<'a> MonitorAlerts<'a> {
+    pub fn project<S>(&mut self, project: S) -> &mut MonitorAlerts<'a>
+    where
+        S: Into<String>,
+    {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn artifact<S>(&mut self, artifact: S) -> &mut MonitorAlerts<'a>
+    where
+        S: Into<String>,
+    {
+        self.artifact = Some(artifact.into());
+        self
+    }
+
+    pub fn start(&mut self, dt: DateTime<Utc>) -> &mut MonitorAlerts<'a> {
+        self.start = Some(utils::format_date(&dt));
+        self
+    }
+
+    pub fn end(&mut self, dt: DateTime<Utc>) -> &mut MonitorAlerts<'a> {
+        self.end = Some(utils::format_date(&dt));
+        self
+    }
+
+    pub fn size(&mut self, size: usize) -> &mut MonitorAlerts<'a> {
+        self.size = Some(size);
+        self
+    }
+}
+
+impl_send!(MonitorAlerts);
+
+impl_paged!(MonitorAlerts);
+
+impl PassiveTotal {
+    pub fn monitor(&self) -> MonitorRequest {
+        MonitorRequest { pt: self }
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use {PassiveTotalError, Request, Result};
+
+/// A request to an endpoint that returns its results a page at a time
+pub trait Paged: Request {
+    /// Request the given page of results, counting from 0
+    fn set_page(&mut self, page: usize);
+
+    /// Returns the items in a page of results
+    fn page_items(&self, resp: &Value) -> Vec<Value>;
+
+    /// Returns an iterator over the items on every page of results
+    fn pages(self) -> Pages<Self>
+    where
+        Self: Sized,
+    {
+        Pages::new(self)
+    }
+}
+
+/// Iterator over the items on every page of a `Paged` request.
+///
+/// Pages are requested as they are needed, starting from the first page, until
+/// a page is empty, shorter than an earlier page or the same as the page before
+/// it, or the `totalRecords` in the response have all been returned. Use
+/// `max_pages` and `max_items` to limit the number of requests made against the
+/// quota.
+///
+/// Items are returned as `Value`s, or parsed into another type with `items`.
+/// If a page can not be fetched the error is returned and the iterator ends.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{Paged, PassiveTotal};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let pages = pt.whois().search_keyword("passivetotal").pages().max_pages(5);
+///
+/// for item in pages {
+///     println!("{}", item.unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Pages<R, T = Value> {
+    request: R,
+    max_pages: Option<usize>,
+    max_items: Option<usize>,
+    page: usize,
+    returned: usize,
+    largest: usize,
+    previous: Vec<Value>,
+    buffer: VecDeque<Value>,
+    done: bool,
+    item: PhantomData<T>,
+}
+
+impl<R> Pages<R>
+where
+    R: Paged,
+{
+    /// Create an iterator over the items on every page of `request`
+    pub fn new(request: R) -> Pages<R> {
+        Pages {
+            request,
+            max_pages: None,
+            max_items: None,
+            page: 0,
+            returned: 0,
+            largest: 0,
+            previous: Vec::new(),
+            buffer: VecDeque::new(),
+            done: false,
+            item: PhantomData,
+        }
+    }
+}
+
+impl<R, T> Pages<R, T>
+where
+    R: Paged,
+    T: DeserializeOwned,
+{
+    /// Request at most `max` pages
+    pub fn max_pages(mut self, max: usize) -> Pages<R, T> {
+        self.max_pages = Some(max);
+        self
+    }
+
+    /// Return at most `max` items, requesting no more pages once they have
+    /// been returned
+    pub fn max_items(mut self, max: usize) -> Pages<R, T> {
+        self.max_items = Some(max);
+        self
+    }
+
+    /// Parse each item into `U`. Items that can not be parsed are returned as
+    /// a `PassiveTotalError::ItemParseError`.
+    pub fn items<U>(self) -> Pages<R, U>
+    where
+        U: DeserializeOwned,
+    {
+        Pages {
+            request: self.request,
+            max_pages: self.max_pages,
+            max_items: self.max_items,
+            page: self.page,
+            returned: self.returned,
+            largest: self.largest,
+            previous: self.previous,
+            buffer: self.buffer,
+            done: self.done,
+            item: PhantomData,
+        }
+    }
+
+    /// The number of pages requested so far
+    pub fn pages_fetched(&self) -> usize {
+        self.page
+    }
+
+    // Request the next page and queue its items, marking the iterator done
+    // if there are no more pages after it
+    fn fetch(&mut self) -> Result<()> {
+        if self.max_pages.map_or(false, |max| self.page >= max) {
+            self.done = true;
+            return Ok(());
+        }
+
+        self.request.set_page(self.page);
+        self.page += 1;
+        let resp = match self.request.send() {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.done = true;
+                return Err(e);
+            },
+        };
+        let items = self.request.page_items(&resp);
+
+        // Endpoints that ignore the page return the same page every time
+        if items.is_empty() || items == self.previous {
+            self.done = true;
+            return Ok(());
+        }
+        if items.len() < self.largest {
+            self.done = true;
+        }
+        self.largest = self.largest.max(items.len());
+
+        let total = resp.get("totalRecords").and_then(Value::as_u64);
+        let seen = self.returned + self.buffer.len() + items.len();
+        if total.map_or(false, |total| seen as u64 >= total) {
+            self.done = true;
+        }
+
+        self.buffer.extend(items.iter().cloned());
+        self.previous = items;
+        Ok(())
+    }
+}
+
+impl<R, T> Iterator for Pages<R, T>
+where
+    R: Paged,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.max_items.map_or(false, |max| self.returned >= max) {
+            return None;
+        }
+
+        while self.buffer.is_empty() && !self.done {
+            if let Err(e) = self.fetch() {
+                return Some(Err(e));
+            }
+        }
+
+        let item = self.buffer.pop_front()?;
+        self.returned += 1;
+        Some(
+            serde_json::from_value(item)
+                .map_err(|e| PassiveTotalError::ItemParseError(e.to_string())),
+        )
+    }
+}
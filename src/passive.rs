@@ -1,33 +1,34 @@
 use serde_json::Value;
 
-use {PassiveTotal, Result};
+use query::HOST_KINDS;
+use {PassiveTotal, Query, Result};
 
 const URL_PDNS: &str = "/dns/passive";
 const URL_PDNS_UNIQUE: &str = "/dns/passive/unique";
 
 request_struct!(PassiveDnsRequest {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(PassiveDnsRequestUnique {
-    query: &'a str,
+    query: Query,
 });
 
 impl PassiveTotal {
-    pub fn passive_dns<'a>(&'a self, query: &'a str) -> PassiveDnsRequest<'a> {
-        PassiveDnsRequest {
+    pub fn passive_dns<'a>(&'a self, query: &str) -> Result<PassiveDnsRequest<'a>> {
+        Ok(PassiveDnsRequest {
             pt: self,
             url: URL_PDNS,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 
-    pub fn passive_dns_unique<'a>(&'a self, query: &'a str) -> PassiveDnsRequestUnique<'a> {
-        PassiveDnsRequestUnique {
+    pub fn passive_dns_unique<'a>(&'a self, query: &str) -> Result<PassiveDnsRequestUnique<'a>> {
+        Ok(PassiveDnsRequestUnique {
             pt: self,
             url: URL_PDNS_UNIQUE,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 }
 
@@ -0,0 +1,543 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use query::{HOST_KINDS, IP_KINDS};
+use whois::WhoisField;
+use {PassiveTotal, PassiveTotalError, Query, QueryKind, Result};
+
+const DEFAULT_DEPTH: usize = 2;
+const DEFAULT_FAN_OUT: usize = 25;
+const DEFAULT_MAX_NODES: usize = 250;
+
+/// A way of finding related indicators from an indicator
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pivot {
+    /// Domains to the IPs they resolved to and IPs to the domains that
+    /// resolved to them, from passive DNS
+    PassiveDns,
+    /// Domains to the email addresses in their WHOIS record
+    WhoisEmail,
+    /// Email addresses to the domains registered with them, from a WHOIS
+    /// search
+    WhoisDomains,
+    /// IPs to the SSL certificates they served, from SSL certificate history
+    SslCertificates,
+    /// SSL certificates to the IPs that served them, from SSL certificate
+    /// history
+    SslIps,
+    /// Domains to their subdomains, from enrichment
+    Subdomains,
+}
+
+/// Every `Pivot`, in the order they are tried
+pub const ALL_PIVOTS: &[Pivot] = &[
+    Pivot::PassiveDns,
+    Pivot::WhoisEmail,
+    Pivot::WhoisDomains,
+    Pivot::SslCertificates,
+    Pivot::SslIps,
+    Pivot::Subdomains,
+];
+
+/// An indicator found while expanding from a seed
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PivotNode {
+    /// The indicator
+    pub query: Query,
+    /// The number of pivots from the seed, which has a depth of 0
+    pub depth: usize,
+    /// The pivot that first found the indicator, `None` for the seed
+    pub pivot: Option<Pivot>,
+    /// The indicator the pivot was made from, `None` for the seed
+    pub from: Option<Query>,
+}
+
+/// A pivot from one indicator that found another
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PivotEdge {
+    pub from: Query,
+    pub to: Query,
+    pub pivot: Pivot,
+}
+
+/// A pivot that failed, which does not stop the rest of the expansion
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PivotError {
+    pub query: Query,
+    pub pivot: Pivot,
+    pub error: String,
+}
+
+/// The indicators found by `Pivoter::expand` and how they were found
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Expansion {
+    /// Each indicator found once, in the order they were found, starting
+    /// with the seed
+    pub nodes: Vec<PivotNode>,
+    /// Every pivot result, including those that found an indicator that was
+    /// already known
+    pub edges: Vec<PivotEdge>,
+    /// The pivots that failed
+    pub errors: Vec<PivotError>,
+    /// Set if some results were left out because of the fan-out or node
+    /// limits
+    pub truncated: bool,
+}
+
+impl Pivot {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Pivot::PassiveDns => "passive-dns",
+            Pivot::WhoisEmail => "whois-email",
+            Pivot::WhoisDomains => "whois-domains",
+            Pivot::SslCertificates => "ssl-certificates",
+            Pivot::SslIps => "ssl-ips",
+            Pivot::Subdomains => "subdomains",
+        }
+    }
+
+    /// Check if this pivot can be made from `query`
+    pub fn applies_to(&self, query: &Query) -> bool {
+        match (*self, query.kind()) {
+            (Pivot::PassiveDns, kind) => HOST_KINDS.contains(&kind),
+            (Pivot::WhoisEmail, kind) | (Pivot::Subdomains, kind) => kind == QueryKind::Domain,
+            (Pivot::WhoisDomains, kind) => kind == QueryKind::Email,
+            (Pivot::SslCertificates, kind) => IP_KINDS.contains(&kind),
+            (Pivot::SslIps, kind) => kind == QueryKind::Certificate,
+        }
+    }
+
+    /// Make this pivot from `query`, returning the indicators it found in
+    /// the order the API returned them, without duplicates
+    pub fn run(&self, pt: &PassiveTotal, query: &Query) -> Result<Vec<Query>> {
+        let q = query.to_string();
+        let found = match *self {
+            Pivot::PassiveDns => {
+                let resp = pt.passive_dns(&q)?.send()?;
+                parse_all(result_fields(&resp, "resolve"), HOST_KINDS)
+            },
+            Pivot::WhoisEmail => {
+                let resp = pt.whois().information(&q)?.send()?;
+                let mut emails = Vec::new();
+                collect_emails(&resp, &mut emails);
+                parse_all(emails, &[QueryKind::Email])
+            },
+            Pivot::WhoisDomains => {
+                let resp = pt.whois().search_field(&q, WhoisField::Email)?.send()?;
+                parse_all(result_fields(&resp, "domain"), &[QueryKind::Domain])
+            },
+            Pivot::SslCertificates => {
+                let resp = pt.ssl().history(&q)?.send()?;
+                parse_all(result_fields(&resp, "sha1"), &[QueryKind::Certificate])
+            },
+            Pivot::SslIps => {
+                let resp = pt.ssl().history(&q)?.send()?;
+                parse_all(result_fields(&resp, "ipAddresses"), IP_KINDS)
+            },
+            Pivot::Subdomains => {
+                let resp = pt.enrichment().subdomains(&q)?.send()?;
+                subdomains(&resp, query).1
+            },
+        };
+
+        Ok(found.into_iter().filter(|found| found != query).collect())
+    }
+}
+
+impl fmt::Display for Pivot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Pivot {
+    type Err = PassiveTotalError;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        ALL_PIVOTS
+            .iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| PassiveTotalError::PivotParseError(String::from(s)))
+    }
+}
+
+impl Serialize for Pivot {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+// Returns the strings in `field` of each item in the response's `results`,
+// including the strings in fields that are arrays
+pub(crate) fn result_fields(resp: &Value, field: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    if let Some(Value::Array(results)) = resp.get("results") {
+        for result in results {
+            match result.get(field) {
+                Some(Value::String(s)) => values.push(s.clone()),
+                Some(Value::Array(items)) => {
+                    values.extend(items.iter().filter_map(Value::as_str).map(String::from))
+                },
+                _ => {},
+            }
+        }
+    }
+    values
+}
+
+// Collect the email addresses anywhere in a WHOIS record, such as those of
+// the registrant and the admin and tech contacts
+pub(crate) fn collect_emails(value: &Value, emails: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(s) if key == "email" || key == "contactEmail" => {
+                        emails.push(s.clone())
+                    },
+                    _ => collect_emails(value, emails),
+                }
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                collect_emails(item, emails);
+            }
+        },
+        _ => {},
+    }
+}
+
+// Parse the values that are valid for one of `kinds`, skipping the rest and
+// any duplicates
+pub(crate) fn parse_all(values: Vec<String>, kinds: &[QueryKind]) -> Vec<Query> {
+    let mut queries: Vec<Query> = Vec::new();
+    for value in values {
+        if let Ok(query) = Query::parse_any(&value, kinds) {
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+    }
+    queries
+}
+
+// Returns the domain an enrichment subdomains response is for and its
+// subdomains. The response lists subdomains as labels relative to its
+// `queryValue`, or to `domain` if it has none.
+pub(crate) fn subdomains(resp: &Value, domain: &Query) -> (Query, Vec<Query>) {
+    let domain = resp
+        .get("queryValue")
+        .and_then(Value::as_str)
+        .and_then(|value| Query::parse_as(value, QueryKind::Domain).ok())
+        .unwrap_or_else(|| domain.clone());
+    let subdomains = match resp.get("subdomains") {
+        Some(Value::Array(subdomains)) => subdomains
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|sub| format!("{}.{}", sub, domain))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let subdomains = parse_all(subdomains, &[QueryKind::Domain]);
+    (domain, subdomains)
+}
+
+/// Expands from a seed indicator to related infrastructure by repeatedly
+/// making `Pivot`s from the indicators found.
+///
+/// Expansion is breadth first and bounded by the maximum depth, the number of
+/// results used from each pivot (the fan-out) and the total number of
+/// indicators found. By default every pivot is made, to a depth of 2 with a
+/// fan-out of 25 and at most 250 indicators.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{PassiveTotal, Pivot, Pivoter, Query};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let expansion = Pivoter::new(&pt)
+///     .pivots(vec![Pivot::PassiveDns, Pivot::WhoisEmail, Pivot::WhoisDomains])
+///     .max_depth(3)
+///     .expand(Query::parse("passivetotal.org").unwrap());
+///
+/// for node in expansion.nodes {
+///     println!("{} via {:?} from {:?}", node.query, node.pivot, node.from);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Pivoter<'a> {
+    pt: &'a PassiveTotal,
+    pivots: Vec<Pivot>,
+    max_depth: usize,
+    max_fan_out: usize,
+    max_nodes: usize,
+}
+
+impl<'a> Pivoter<'a> {
+    /// Create a `Pivoter` that makes every pivot with the default limits
+    pub fn new(pt: &'a PassiveTotal) -> Pivoter<'a> {
+        Pivoter {
+            pt,
+            pivots: ALL_PIVOTS.to_vec(),
+            max_depth: DEFAULT_DEPTH,
+            max_fan_out: DEFAULT_FAN_OUT,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+
+    /// Only make these pivots
+    pub fn pivots(mut self, pivots: Vec<Pivot>) -> Pivoter<'a> {
+        self.pivots = pivots;
+        self
+    }
+
+    /// Stop pivoting from indicators this many pivots away from the seed
+    pub fn max_depth(mut self, depth: usize) -> Pivoter<'a> {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Use at most this many results from each pivot
+    pub fn max_fan_out(mut self, fan_out: usize) -> Pivoter<'a> {
+        self.max_fan_out = fan_out;
+        self
+    }
+
+    /// Stop adding indicators once this many have been found, including the
+    /// seed
+    pub fn max_nodes(mut self, nodes: usize) -> Pivoter<'a> {
+        self.max_nodes = nodes;
+        self
+    }
+
+    /// Expand from `seed`, returning every indicator found.
+    ///
+    /// No more pivots are made once `max_nodes` indicators have been found.
+    pub fn expand(&self, seed: Query) -> Expansion {
+        self.expand_with(seed, |pivot, query| pivot.run(self.pt, query))
+    }
+
+    // Expand from `seed`, making each pivot with `run`
+    fn expand_with<F>(&self, seed: Query, mut run: F) -> Expansion
+    where
+        F: FnMut(Pivot, &Query) -> Result<Vec<Query>>,
+    {
+        let mut expansion = Expansion::default();
+        let mut known: HashSet<Query> = HashSet::new();
+        let mut queue: VecDeque<(Query, usize)> = VecDeque::new();
+
+        known.insert(seed.clone());
+        expansion.nodes.push(PivotNode {
+            query: seed.clone(),
+            depth: 0,
+            pivot: None,
+            from: None,
+        });
+        queue.push_back((seed, 0));
+
+        while let Some((query, depth)) = queue.pop_front() {
+            if depth >= self.max_depth {
+                continue;
+            }
+
+            for pivot in self.pivots.iter().filter(|p| p.applies_to(&query)) {
+                // Pivots can not add any more indicators, so save the quota
+                if known.len() >= self.max_nodes {
+                    expansion.truncated = true;
+                    return expansion;
+                }
+
+                let mut found = match run(*pivot, &query) {
+                    Ok(found) => found,
+                    Err(e) => {
+                        expansion.errors.push(PivotError {
+                            query: query.clone(),
+                            pivot: *pivot,
+                            error: e.to_string(),
+                        });
+                        continue;
+                    },
+                };
+                if found.len() > self.max_fan_out {
+                    found.truncate(self.max_fan_out);
+                    expansion.truncated = true;
+                }
+
+                for to in found {
+                    if !known.contains(&to) {
+                        if known.len() >= self.max_nodes {
+                            expansion.truncated = true;
+                            continue;
+                        }
+                        known.insert(to.clone());
+                        expansion.nodes.push(PivotNode {
+                            query: to.clone(),
+                            depth: depth + 1,
+                            pivot: Some(*pivot),
+                            from: Some(query.clone()),
+                        });
+                        queue.push_back((to.clone(), depth + 1));
+                    }
+                    expansion.edges.push(PivotEdge {
+                        from: query.clone(),
+                        to,
+                        pivot: *pivot,
+                    });
+                }
+            }
+        }
+
+        expansion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(s: &str) -> Query {
+        Query::parse(s).unwrap()
+    }
+
+    fn queries(values: &[&str]) -> Vec<Query> {
+        values.iter().map(|v| q(v)).collect()
+    }
+
+    // A small network: evil.com resolves to two IPs, one of which is shared
+    // with other.com, and its WHOIS lookup fails
+    fn fake_run(pivot: Pivot, query: &Query) -> Result<Vec<Query>> {
+        match (pivot, query.to_string().as_str()) {
+            (Pivot::PassiveDns, "evil.com") => Ok(queries(&["1.2.3.4", "5.6.7.8"])),
+            (Pivot::PassiveDns, "1.2.3.4") => Ok(queries(&["evil.com", "other.com"])),
+            (Pivot::WhoisEmail, "evil.com") => {
+                Err(PassiveTotalError::ConfigError(String::from("no whois")))
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn pivoter<'a>(pt: &'a PassiveTotal) -> Pivoter<'a> {
+        Pivoter::new(pt).pivots(vec![Pivot::PassiveDns, Pivot::WhoisEmail])
+    }
+
+    #[test]
+    fn result_fields_of_strings_and_arrays() {
+        let resp = json!({
+            "results": [
+                {"resolve": "1.2.3.4"},
+                {"resolve": ["5.6.7.8", 1, "9.9.9.9"]},
+                {"other": "x"},
+            ]
+        });
+        assert_eq!(result_fields(&resp, "resolve"), vec!["1.2.3.4", "5.6.7.8", "9.9.9.9"]);
+        assert!(result_fields(&json!({}), "resolve").is_empty());
+    }
+
+    #[test]
+    fn emails_anywhere_in_whois() {
+        let whois = json!({
+            "contactEmail": "abuse@registrar.com",
+            "registrant": {"email": "owner@example.com", "name": "Owner"},
+            "contacts": [{"admin": {"email": "admin@example.com"}}],
+            "name": "not@an-email-field.com",
+        });
+        let mut emails = Vec::new();
+        collect_emails(&whois, &mut emails);
+        emails.sort();
+        assert_eq!(emails, vec!["abuse@registrar.com", "admin@example.com", "owner@example.com"]);
+    }
+
+    #[test]
+    fn parse_all_skips_invalid_and_duplicates() {
+        let values = vec!["Evil.com", "not a domain", "evil[.]com", "1.2.3.4", "other.com"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(parse_all(values, &[QueryKind::Domain]), queries(&["evil.com", "other.com"]));
+    }
+
+    #[test]
+    fn expansion_records_nodes_edges_and_errors() {
+        let pt = PassiveTotal::with_auth("username", "apikey");
+        let expansion = pivoter(&pt).expand_with(q("evil.com"), fake_run);
+
+        let nodes: Vec<_> = expansion
+            .nodes
+            .iter()
+            .map(|n| (n.query.to_string(), n.depth, n.from.as_ref().map(Query::to_string)))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (String::from("evil.com"), 0, None),
+                (String::from("1.2.3.4"), 1, Some(String::from("evil.com"))),
+                (String::from("5.6.7.8"), 1, Some(String::from("evil.com"))),
+                (String::from("other.com"), 2, Some(String::from("1.2.3.4"))),
+            ]
+        );
+
+        // The edge back to the already known seed is kept
+        let edges: Vec<_> = expansion
+            .edges
+            .iter()
+            .map(|e| (e.from.to_string(), e.to.to_string()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (String::from("evil.com"), String::from("1.2.3.4")),
+                (String::from("evil.com"), String::from("5.6.7.8")),
+                (String::from("1.2.3.4"), String::from("evil.com")),
+                (String::from("1.2.3.4"), String::from("other.com")),
+            ]
+        );
+        assert!(expansion.edges.iter().all(|e| e.pivot == Pivot::PassiveDns));
+
+        assert_eq!(expansion.errors.len(), 1);
+        assert_eq!(expansion.errors[0].pivot, Pivot::WhoisEmail);
+        assert_eq!(expansion.errors[0].query, q("evil.com"));
+        assert!(!expansion.truncated);
+    }
+
+    #[test]
+    fn max_depth() {
+        let pt = PassiveTotal::with_auth("username", "apikey");
+        let expansion = pivoter(&pt).max_depth(1).expand_with(q("evil.com"), fake_run);
+        assert_eq!(expansion.nodes.len(), 3);
+        assert!(!expansion.truncated);
+    }
+
+    #[test]
+    fn fan_out_truncates() {
+        let pt = PassiveTotal::with_auth("username", "apikey");
+        let expansion = pivoter(&pt).max_fan_out(1).expand_with(q("evil.com"), fake_run);
+
+        let found: Vec<_> = expansion.nodes.iter().map(|n| n.query.to_string()).collect();
+        assert_eq!(found, vec!["evil.com", "1.2.3.4"]);
+        assert!(expansion.truncated);
+    }
+
+    #[test]
+    fn max_nodes_stops_pivoting() {
+        let pt = PassiveTotal::with_auth("username", "apikey");
+        let mut calls = Vec::new();
+        let expansion = pivoter(&pt).max_nodes(2).expand_with(q("evil.com"), |pivot, query| {
+            calls.push((pivot, query.to_string()));
+            fake_run(pivot, query)
+        });
+
+        let found: Vec<_> = expansion.nodes.iter().map(|n| n.query.to_string()).collect();
+        assert_eq!(found, vec!["evil.com", "1.2.3.4"]);
+        assert!(expansion.truncated);
+        // Only the first pivot was made, the limit was reached by its results
+        assert_eq!(calls, vec![(Pivot::PassiveDns, String::from("evil.com"))]);
+    }
+}
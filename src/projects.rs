@@ -0,0 +1,99 @@
+use serde_json::Value;
+
+use {PassiveTotal, Result};
+
+const URL_PROJECT: &str = "/project";
+
+pub struct ProjectsRequest<'a> {
+    pt: &'a PassiveTotal,
+}
+
+request_struct!(ProjectsList {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    organization: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visibility: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    featured: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+});
+
+impl<'a> ProjectsRequest<'a> {
+    pub fn list(self) -> ProjectsList<'a> {
+        ProjectsList {
+            pt: self.pt,
+            url: URL_PROJECT,
+            project: None,
+            owner: None,
+            creator: None,
+            organization: None,
+            visibility: None,
+            featured: None,
+            page: None,
+        }
+    }
+}
+
+impl<'a> ProjectsList<'a> {
+    pub fn project<S>(&mut self, project: S) -> &mut ProjectsList<'a>
+    where
+        S: Into<String>,
+    {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn owner<S>(&mut self, owner: S) -> &mut ProjectsList<'a>
+    where
+        S: Into<String>,
+    {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    pub fn creator<S>(&mut self, creator: S) -> &mut ProjectsList<'a>
+    where
+        S: Into<String>,
+    {
+        self.creator = Some(creator.into());
+        self
+    }
+
+    pub fn organization<S>(&mut self, organization: S) -> &mut ProjectsList<'a>
+    where
+        S: Into<String>,
+    {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    pub fn visibility<S>(&mut self, visibility: S) -> &mut ProjectsList<'a>
+    where
+        S: Into<String>,
+    {
+        self.visibility = Some(visibility.into());
+        self
+    }
+
+    pub fn featured(&mut self, featured: bool) -> &mut ProjectsList<'a> {
+        self.featured = Some(featured);
+        self
+    }
+}
+
+impl_send!(ProjectsList);
+
+impl_paged!(ProjectsList);
+
+impl PassiveTotal {
+    pub fn projects(&self) -> ProjectsRequest {
+        ProjectsRequest { pt: self }
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use url::Host;
+
+use utils;
+use {PassiveTotalError, Result};
+
+/// Query kinds accepted by endpoints that look up hosts
+pub const HOST_KINDS: &[QueryKind] = &[QueryKind::Domain, QueryKind::Ipv4, QueryKind::Ipv6];
+
+/// Query kinds accepted by endpoints that look up IP addresses
+pub const IP_KINDS: &[QueryKind] = &[QueryKind::Ipv4, QueryKind::Ipv6];
+
+// Order used when guessing the kind of an untyped query. Hashes must be tried
+// before domains as a bare hex string is also a valid single label domain.
+const ALL_KINDS: &[QueryKind] = &[
+    QueryKind::Email,
+    QueryKind::Ipv4,
+    QueryKind::Ipv6,
+    QueryKind::Certificate,
+    QueryKind::Hash,
+    QueryKind::Domain,
+];
+
+/// The kinds of indicator that can be used as a `Query`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Domain,
+    Ipv4,
+    Ipv6,
+    Email,
+    Hash,
+    Certificate,
+}
+
+/// A validated and normalized indicator to send to the Passivetotal API
+///
+/// URLs are reduced to their host, domains are lowercased and hashes and
+/// certificate fingerprints are lowercased with any `:` separators removed.
+/// IPv6 addresses are compressed and IPv4-mapped IPv6 addresses are
+/// converted to the `Ipv4` they represent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Query {
+    Domain(String),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Email(String),
+    Hash(String),
+    Certificate(String),
+}
+
+impl QueryKind {
+    /// Returns a `&str` representation of a `QueryKind` enum
+    pub fn as_str(&self) -> &str {
+        match *self {
+            QueryKind::Domain => "domain",
+            QueryKind::Ipv4 => "ipv4",
+            QueryKind::Ipv6 => "ipv6",
+            QueryKind::Email => "email",
+            QueryKind::Hash => "hash",
+            QueryKind::Certificate => "certificate",
+        }
+    }
+}
+
+impl fmt::Display for QueryKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Query {
+    /// Parse a query, guessing its kind.
+    ///
+    /// A 40 character hex string is treated as a certificate SHA-1, use
+    /// `parse_as` with `QueryKind::Hash` to treat it as a file hash instead.
+    pub fn parse(query: &str) -> Result<Query> {
+        Query::parse_any(query, ALL_KINDS)
+    }
+
+    /// Parse a query as the first of the given kinds that matches.
+    pub fn parse_any(query: &str, kinds: &[QueryKind]) -> Result<Query> {
+        if kinds.len() == 1 {
+            return Query::parse_as(query, kinds[0]);
+        }
+
+        for kind in kinds {
+            if let Ok(q) = Query::parse_as(query, *kind) {
+                return Ok(q);
+            }
+        }
+
+        Err(PassiveTotalError::InvalidQuery(
+            String::from(query),
+            kinds.to_vec(),
+        ))
+    }
+
+    /// Parse a query as the given kind.
+    ///
+    /// Every kind of query is passed through `refang` before parsing, so
+    /// defanged queries such as `evil[.]com` or `hxxp://evil[.]com` are
+    /// accepted. `parse`, `parse_any` and the request constructors all parse
+    /// through this method and accept defanged queries too.
+    pub fn parse_as(query: &str, kind: QueryKind) -> Result<Query> {
+        let refanged = utils::refang(query);
+        let query = refanged.as_str();
+        let parsed = match kind {
+            QueryKind::Domain => match utils::parse_host(query)? {
+                Host::Domain(domain) => Some(Query::Domain(domain)),
+                _ => return Err(PassiveTotalError::InvalidDomain),
+            },
+            QueryKind::Ipv4 => match utils::parse_host(query) {
+                Ok(Host::Ipv4(ip)) => Some(Query::Ipv4(ip)),
+                _ => None,
+            },
+            QueryKind::Ipv6 => match utils::parse_host(query) {
+                Ok(Host::Ipv6(ip)) => Some(Query::Ipv6(ip)),
+                _ => None,
+            },
+            QueryKind::Email => utils::valid_email(query).map(Query::Email),
+            QueryKind::Hash => utils::valid_hash(query, &[32, 40, 64]).map(Query::Hash),
+            QueryKind::Certificate => {
+                utils::valid_hash(&query.replace(':', ""), &[40]).map(Query::Certificate)
+            },
+        };
+
+        parsed.ok_or_else(|| PassiveTotalError::InvalidQuery(String::from(query), vec![kind]))
+    }
+
+    /// Returns the `QueryKind` of this query
+    pub fn kind(&self) -> QueryKind {
+        match *self {
+            Query::Domain(_) => QueryKind::Domain,
+            Query::Ipv4(_) => QueryKind::Ipv4,
+            Query::Ipv6(_) => QueryKind::Ipv6,
+            Query::Email(_) => QueryKind::Email,
+            Query::Hash(_) => QueryKind::Hash,
+            Query::Certificate(_) => QueryKind::Certificate,
+        }
+    }
+
+    /// Returns this query formatted for display, with any punycode in
+    /// domains and email addresses converted to Unicode.
+    pub fn to_unicode(&self) -> String {
+        match *self {
+            Query::Domain(ref s) => utils::domain_to_unicode(s),
+            Query::Email(ref s) => match s.rfind('@') {
+                Some(i) => format!("{}@{}", &s[..i], utils::domain_to_unicode(&s[i + 1..])),
+                None => s.clone(),
+            },
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Query::Ipv4(ref ip) => write!(f, "{}", ip),
+            Query::Ipv6(ref ip) => write!(f, "{}", ip),
+            Query::Domain(ref s) |
+            Query::Email(ref s) |
+            Query::Hash(ref s) |
+            Query::Certificate(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = PassiveTotalError;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+impl Serialize for Query {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
@@ -0,0 +1,38 @@
+use std::cmp;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Spaces out requests so that no more than a given number are started per
+/// minute.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests: u32) -> RateLimiter {
+        RateLimiter {
+            interval: Duration::from_secs(60) / cmp::max(requests, 1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    // Block until the next request is allowed to start. The next slot is
+    // claimed before sleeping so concurrent callers queue up behind each
+    // other.
+    pub fn wait(&self) {
+        let now = Instant::now();
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = cmp::max(*next, now);
+            *next = slot + self.interval;
+            slot
+        };
+
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
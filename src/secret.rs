@@ -0,0 +1,70 @@
+use std::fmt;
+use std::ptr;
+
+const REDACTED: &str = "[REDACTED]";
+
+// Overwrite the bytes of `s` with zeros and clear it. The writes are
+// volatile so they are not optimised away as stores to memory that is about
+// to be freed.
+pub(crate) fn zero(s: &mut String) {
+    // Zero bytes are valid UTF-8
+    unsafe {
+        for byte in s.as_mut_vec().iter_mut() {
+            ptr::write_volatile(byte, 0);
+        }
+    }
+    s.clear();
+}
+
+/// A credential such as an API key.
+///
+/// The wrapped value is redacted when formatted with `Debug` or `Display`
+/// and is zeroed in memory when dropped. `Secret` deliberately does not
+/// implement `Serialize` so it can not be written out by accident. Use
+/// `expose` to access the value.
+#[derive(Clone, Deserialize)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<S>(secret: S) -> Secret
+    where
+        S: Into<String>,
+    {
+        Secret(secret.into())
+    }
+
+    /// Returns the wrapped value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        zero(&mut self.0);
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Secret {
+        Secret(secret)
+    }
+}
+
+impl<'a> From<&'a str> for Secret {
+    fn from(secret: &'a str) -> Secret {
+        Secret(String::from(secret))
+    }
+}
@@ -3,27 +3,32 @@ use std::str::FromStr;
 use serde::{Serialize, Serializer};
 use serde_json::Value;
 
-use {PassiveTotal, PassiveTotalError, Result};
+use {PassiveTotal, PassiveTotalError, Query, QueryKind, Result};
 
 const URL_SSL: &str = "/ssl-certificate";
 const URL_HISTORY: &str = "/ssl-certificate/history";
 const URL_KEYWORD: &str = "/ssl-certificate/search/keyword";
 const URL_SEARCH: &str = "/ssl-certificate/search";
 
+// SSL certificate history can be looked up by certificate or by IP address
+const HISTORY_KINDS: &[QueryKind] = &[QueryKind::Certificate, QueryKind::Ipv4, QueryKind::Ipv6];
+
 pub struct SslRequest<'a> {
     pt: &'a PassiveTotal,
 }
 
 request_struct!(SslCertificate {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(SslHistory {
-    query: &'a str
+    query: Query,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
 });
 
 request_struct!(SslSearchField {
-    query: &'a str,
+    query: String,
     field: SslField,
 });
 
@@ -96,6 +101,18 @@ impl SslField {
             SslField::IssuerEmailAddress => "issuerEmailAddress",
         }
     }
+
+    // Returns the `QueryKind` a query must be for searches on this field, if
+    // the field is not free text
+    fn query_kind(&self) -> Option<QueryKind> {
+        match *self {
+            SslField::Sha1 => Some(QueryKind::Certificate),
+            SslField::SubjectEmailAddress | SslField::IssuerEmailAddress => {
+                Some(QueryKind::Email)
+            },
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for SslField {
@@ -146,20 +163,21 @@ impl Serialize for SslField {
 }
 
 impl<'a> SslRequest<'a> {
-    pub fn certificate(self, query: &'a str) -> SslCertificate {
-        SslCertificate {
+    pub fn certificate(self, query: &str) -> Result<SslCertificate<'a>> {
+        Ok(SslCertificate {
             pt: self.pt,
             url: URL_SSL,
-            query,
-        }
+            query: Query::parse_as(query, QueryKind::Certificate)?,
+        })
     }
 
-    pub fn history(self, query: &'a str) -> SslHistory {
-        SslHistory {
+    pub fn history(self, query: &str) -> Result<SslHistory<'a>> {
+        Ok(SslHistory {
             pt: self.pt,
             url: URL_HISTORY,
-            query,
-        }
+            query: Query::parse_any(query, HISTORY_KINDS)?,
+            page: None,
+        })
     }
 
     pub fn search_keyword(self, query: &'a str) -> SslSearchKeyword {
@@ -170,13 +188,18 @@ impl<'a> SslRequest<'a> {
         }
     }
 
-    pub fn search_field(self, query: &'a str, field: SslField) -> SslSearchField {
-        SslSearchField {
+    pub fn search_field(self, query: &str, field: SslField) -> Result<SslSearchField<'a>> {
+        let query = match field.query_kind() {
+            Some(kind) => Query::parse_as(query, kind)?.to_string(),
+            None => String::from(query),
+        };
+
+        Ok(SslSearchField {
             pt: self.pt,
             url: URL_SEARCH,
             query,
             field,
-        }
+        })
     }
 }
 
@@ -185,6 +208,8 @@ impl_send!(SslHistory);
 impl_send!(SslSearchField);
 impl_send!(SslSearchKeyword);
 
+impl_paged!(SslHistory);
+
 impl PassiveTotal {
     pub fn ssl(&self) -> SslRequest {
         SslRequest { pt: self }
@@ -0,0 +1,313 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dirs;
+use serde_json::{self, Value};
+
+use diskcache::{fnv1a, write_atomic};
+use {utils, PassiveTotal, PassiveTotalError, Result};
+
+const CURSOR_FILE: &str = "passivetotal/teamstream-cursor.json";
+const DEFAULT_INTERVAL: u64 = 60;
+
+/// An event from an organization's teamstream
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TeamstreamEvent {
+    /// The user that caused the event
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Where the event came from, such as `web` or `api`
+    #[serde(default)]
+    pub source: Option<String>,
+    /// The kind of event, such as `search` or `tag`
+    #[serde(default, rename = "type")]
+    pub typ: Option<String>,
+    /// What the event was about, such as the query searched for
+    #[serde(default)]
+    pub focus: Option<String>,
+    /// When the event happened, formatted like Passivetotal dates
+    #[serde(default)]
+    pub dt: Option<String>,
+    /// Any further details of the event
+    #[serde(default)]
+    pub additional: Value,
+}
+
+// The position of a follower in the teamstream, saved between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cursor {
+    datetime: Option<String>,
+    // Fingerprints of the events at `datetime` that were already returned,
+    // as the next poll starting from `datetime` returns them again
+    seen: BTreeSet<String>,
+}
+
+/// Follows an organization's teamstream, returning each new event once.
+///
+/// The teamstream is polled with a `datetime` filter that advances to the
+/// latest event returned. With `with_cursor_file` the position is saved after
+/// every batch of events from a poll, so a restarted follower carries on
+/// where it stopped. Only events from a batch that was not finished are
+/// returned again.
+///
+/// The follower is an `Iterator` that blocks until the next event arrives.
+/// Failed polls are returned as errors and the next call polls again.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{PassiveTotal, TeamstreamFollower};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let follower = TeamstreamFollower::new(&pt)
+///     .with_cursor_file(TeamstreamFollower::default_cursor_file().unwrap())
+///     .unwrap();
+///
+/// for event in follower {
+///     println!("{:?}", event);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TeamstreamFollower<'a> {
+    pt: &'a PassiveTotal,
+    source: Option<String>,
+    typ: Option<String>,
+    focus: Option<String>,
+    interval: Duration,
+    cursor: Cursor,
+    cursor_file: Option<PathBuf>,
+    pending: VecDeque<TeamstreamEvent>,
+}
+
+fn cursor_error(path: &Path, e: &fmt::Display) -> PassiveTotalError {
+    PassiveTotalError::ArchiveError(format!("{}: {}", path.display(), e))
+}
+
+impl TeamstreamEvent {
+    /// When the event happened
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        self.dt.as_ref().and_then(|dt| utils::parse_date(dt))
+    }
+
+    // Identifies an event so it can be recognized when it is returned again
+    fn fingerprint(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{:016x}", fnv1a(&json))
+    }
+}
+
+impl<'a> TeamstreamFollower<'a> {
+    /// Create a follower for every event in the teamstream, polling once a
+    /// minute
+    pub fn new(pt: &'a PassiveTotal) -> TeamstreamFollower<'a> {
+        TeamstreamFollower {
+            pt,
+            source: None,
+            typ: None,
+            focus: None,
+            interval: Duration::from_secs(DEFAULT_INTERVAL),
+            cursor: Cursor::default(),
+            cursor_file: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The default cursor file, `passivetotal/teamstream-cursor.json` under
+    /// the user's data directory, such as `~/.local/share` on Linux
+    pub fn default_cursor_file() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(CURSOR_FILE))
+    }
+
+    /// Only follow events from this source
+    pub fn source<S>(mut self, source: S) -> TeamstreamFollower<'a>
+    where
+        S: Into<String>,
+    {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Only follow events of this type
+    pub fn typ<S>(mut self, ty: S) -> TeamstreamFollower<'a>
+    where
+        S: Into<String>,
+    {
+        self.typ = Some(ty.into());
+        self
+    }
+
+    /// Only follow events with this focus
+    pub fn focus<S>(mut self, focus: S) -> TeamstreamFollower<'a>
+    where
+        S: Into<String>,
+    {
+        self.focus = Some(focus.into());
+        self
+    }
+
+    /// Set how long to wait between polls when there are no new events
+    pub fn interval(mut self, interval: Duration) -> TeamstreamFollower<'a> {
+        self.interval = interval;
+        self
+    }
+
+    /// Start from events at or after `dt`, unless a saved cursor is later
+    pub fn since(mut self, dt: DateTime<Utc>) -> TeamstreamFollower<'a> {
+        let later = self
+            .cursor
+            .datetime
+            .as_ref()
+            .and_then(|c| utils::parse_date(c))
+            .map_or(false, |c| c >= dt);
+        if !later {
+            self.cursor = Cursor {
+                datetime: Some(utils::format_date(&dt)),
+                seen: BTreeSet::new(),
+            };
+        }
+        self
+    }
+
+    /// Load the saved position from `path` and save it there after each
+    /// batch of events is returned
+    pub fn with_cursor_file<P>(mut self, path: P) -> Result<TeamstreamFollower<'a>>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        self.cursor = match File::open(&path) {
+            Ok(f) => {
+                serde_json::from_reader(BufReader::new(f)).map_err(|e| cursor_error(&path, &e))?
+            },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Cursor::default(),
+            Err(e) => return Err(cursor_error(&path, &e)),
+        };
+        self.cursor_file = Some(path);
+        Ok(self)
+    }
+
+    /// The datetime of the latest event returned
+    pub fn cursor(&self) -> Option<DateTime<Utc>> {
+        self.cursor.datetime.as_ref().and_then(|c| utils::parse_date(c))
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = match self.cursor_file {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| cursor_error(dir, &e))?;
+        }
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        write_atomic(&tmp, path, &self.cursor).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            cursor_error(path, &e)
+        })
+    }
+
+    /// Poll the teamstream once and return the events that have not been
+    /// returned before, oldest first, without waiting.
+    ///
+    /// The events are also marked as returned, so they are not returned by
+    /// the iterator.
+    pub fn poll(&mut self) -> Result<Vec<TeamstreamEvent>> {
+        let mut events = self.fetch()?;
+        events.extend(self.pending.drain(..));
+        events.sort_by_key(TeamstreamEvent::datetime);
+        for event in &events {
+            self.advance(event);
+        }
+        self.save()?;
+        Ok(events)
+    }
+
+    // Request the events since the cursor that have not been returned yet,
+    // oldest first
+    fn fetch(&self) -> Result<Vec<TeamstreamEvent>> {
+        let mut req = self.pt.account().organization().teamstream();
+        if let Some(dt) = self.cursor() {
+            req.datetime(dt);
+        }
+        if let Some(ref source) = self.source {
+            req.source(source.as_str());
+        }
+        if let Some(ref ty) = self.typ {
+            req.typ(ty.as_str());
+        }
+        if let Some(ref focus) = self.focus {
+            req.focus(focus.as_str());
+        }
+
+        let resp = req.send()?;
+        let cursor = self.cursor();
+        let mut events: Vec<TeamstreamEvent> = resp
+            .get("teamstream")
+            .and_then(Value::as_array)
+            .map(|events| {
+                events
+                    .iter()
+                    .filter_map(|e| serde_json::from_value(e.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        events.retain(|e: &TeamstreamEvent| {
+            let old = match (e.datetime(), cursor) {
+                (Some(dt), Some(cursor)) => dt < cursor,
+                _ => false,
+            };
+            !old && !self.cursor.seen.contains(&e.fingerprint())
+        });
+        events.sort_by_key(TeamstreamEvent::datetime);
+        events.dedup();
+        Ok(events)
+    }
+
+    // Move the cursor past an event that is being returned. The cursor is
+    // saved once the whole batch has been returned.
+    fn advance(&mut self, event: &TeamstreamEvent) {
+        if let Some(dt) = event.datetime() {
+            if self.cursor().map_or(true, |cursor| dt > cursor) {
+                self.cursor.datetime = Some(utils::format_date(&dt));
+                self.cursor.seen.clear();
+            }
+        }
+        self.cursor.seen.insert(event.fingerprint());
+    }
+}
+
+impl<'a> Iterator for TeamstreamFollower<'a> {
+    type Item = Result<TeamstreamEvent>;
+
+    fn next(&mut self) -> Option<Result<TeamstreamEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.advance(&event);
+                if self.pending.is_empty() {
+                    return Some(self.save().map(|_| event));
+                }
+                return Some(Ok(event));
+            }
+
+            match self.fetch() {
+                Ok(events) => {
+                    if events.is_empty() {
+                        thread::sleep(self.interval);
+                    }
+                    self.pending.extend(events);
+                },
+                Err(e) => {
+                    thread::sleep(self.interval);
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
@@ -1,28 +1,445 @@
-use url::{Host, Url};
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use url::{idna, Host, Url};
 
 use {PassiveTotalError, Result};
 
-// Check to see if a given `Host` is either a `Domain` or an `Ipv4`
-// and return it as a `String` or return an `Error`
-fn valid_host(host: Host) -> Result<String> {
+// Return the IPv4 address embedded in an IPv4-mapped IPv6 address
+// (`::ffff:a.b.c.d`), if any
+fn ipv4_mapped(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, hi, lo] => Some(Ipv4Addr::new(
+            (hi >> 8) as u8,
+            hi as u8,
+            (lo >> 8) as u8,
+            lo as u8,
+        )),
+        _ => None,
+    }
+}
+
+// Canonicalize a given `Host`. IPv4-mapped IPv6 addresses are returned as the
+// `Ipv4` they represent as that is how Passivetotal indexes them.
+fn valid_host(host: Host) -> Host {
     match host {
-        Host::Domain(s) => Ok(s.to_owned()),
-        Host::Ipv4(ip) => Ok(format!("{}", ip)),
-        Host::Ipv6(_) => Err(PassiveTotalError::InvalidDomain),
+        Host::Ipv6(ip) => match ipv4_mapped(&ip) {
+            Some(ipv4) => Host::Ipv4(ipv4),
+            None => Host::Ipv6(ip),
+        },
+        _ => host,
     }
 }
 
-// Try to parse a given query into a valid domain or ipv4 address
-pub fn valid_domain(query: &str) -> Result<String> {
+// Try to parse a given query into a valid domain or IP address `Host`,
+// extracting the host from the query if it is a URL. IPv6 addresses may be
+// bare or enclosed in brackets.
+pub fn parse_host(query: &str) -> Result<Host> {
+    if let Ok(ip) = query.parse::<Ipv6Addr>() {
+        return Ok(valid_host(Host::Ipv6(ip)));
+    }
+
     if let Ok(url) = Url::parse(query) {
         if let Some(host) = url.host() {
-            return valid_host(host.to_owned());
+            return Ok(valid_host(host.to_owned()));
         }
     }
 
     if let Ok(host) = Host::parse(query) {
-        return valid_host(host.to_owned());
+        return Ok(valid_host(host));
     }
 
     Err(PassiveTotalError::InvalidDomain)
 }
+
+// Check that a query looks like `local@domain` and return it with the domain
+// normalized
+pub fn valid_email(query: &str) -> Option<String> {
+    let mut parts = query.rsplitn(2, '@');
+    let domain = parts.next()?;
+    let local = parts.next()?;
+
+    if local.is_empty() || local.contains(char::is_whitespace) {
+        return None;
+    }
+
+    match Host::parse(domain) {
+        Ok(Host::Domain(domain)) => Some(format!("{}@{}", local, domain)),
+        _ => None,
+    }
+}
+
+// Check that a query is a hex string of one of the given lengths and return
+// it lowercased
+pub fn valid_hash(query: &str, lengths: &[usize]) -> Option<String> {
+    if lengths.contains(&query.len()) && query.chars().all(|c| c.is_digit(16)) {
+        Some(query.to_lowercase())
+    } else {
+        None
+    }
+}
+
+// Defanged forms of URL schemes and the scheme they represent
+const DEFANGED_SCHEMES: &[(&str, &str)] = &[
+    ("hxxps", "https"),
+    ("hxxp", "http"),
+    ("h**ps", "https"),
+    ("h**p", "http"),
+    ("fxp", "ftp"),
+];
+
+// URL schemes and the defanged form used by `defang`
+const SCHEME_DEFANGS: &[(&str, &str)] = &[("https", "hxxps"), ("http", "hxxp"), ("ftp", "fxp")];
+
+// Defanged forms of separators and the separator they represent
+const DEFANGED_SEPARATORS: &[(&str, &str)] = &[
+    ("[://]", "://"),
+    ("[:]", ":"),
+    ("[.]", "."),
+    ("(.)", "."),
+    ("{.}", "."),
+    ("[dot]", "."),
+    ("(dot)", "."),
+    ("{dot}", "."),
+    ("\\.", "."),
+    ("[@]", "@"),
+    ("(@)", "@"),
+    ("{@}", "@"),
+    ("[at]", "@"),
+    ("(at)", "@"),
+    ("{at}", "@"),
+    ("[/]", "/"),
+];
+
+// Replace all ASCII case-insensitive occurrences of `from` in `s` with `to`
+fn replace_ignore_case(s: &str, from: &str, to: &str) -> String {
+    let lower = s.to_ascii_lowercase();
+    let mut out = String::with_capacity(s.len());
+    let mut last = 0;
+
+    for (i, _) in lower.match_indices(from) {
+        out.push_str(&s[last..i]);
+        out.push_str(to);
+        last = i + from.len();
+    }
+    out.push_str(&s[last..]);
+    out
+}
+
+/// Reverse common indicator defanging conventions.
+///
+/// Converts forms such as `evil[.]com`, `hxxps://bad[.]site/path`,
+/// `1.2.3[.]4` and `user[at]example(dot)com` back into their original form.
+/// Works on single indicators as well as free text. Queries are refanged
+/// before being parsed into a `Query`.
+pub fn refang(query: &str) -> String {
+    let mut s = query.trim().to_owned();
+
+    for &(from, to) in DEFANGED_SEPARATORS {
+        s = replace_ignore_case(&s, from, to);
+    }
+
+    for &(from, to) in DEFANGED_SCHEMES {
+        let prefix = format!("{}://", from);
+        if s.to_ascii_lowercase().starts_with(&prefix) {
+            s = format!("{}{}", to, &s[from.len()..]);
+            break;
+        }
+    }
+
+    s
+}
+
+/// Defang an indicator so that it can be safely included in reports and
+/// chat without being turned into a clickable link.
+///
+/// Any defanging already present is removed first, so defanging a defanged
+/// indicator returns it unchanged.
+pub fn defang(query: &str) -> String {
+    let mut s = refang(query);
+
+    for &(from, to) in SCHEME_DEFANGS {
+        let prefix = format!("{}://", from);
+        if s.to_ascii_lowercase().starts_with(&prefix) {
+            s = format!("{}{}", to, &s[from.len()..]);
+            break;
+        }
+    }
+
+    s.replace('.', "[.]").replace('@', "[@]")
+}
+
+// Unicode scripts used to detect mixed-script domain labels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Georgian,
+    Cherokee,
+    Hangul,
+    Han,
+    Kana,
+    Other,
+}
+
+// Cyrillic and Greek letters that are commonly mistaken for Latin letters
+const LATIN_CONFUSABLES: &str = "аеорсухіјѕԁӏԛԝһвкмнтгпАВЕКМНОРСТХІЈЅαβεικνορτυχΑΒΕΖΗΙΚΜΝΟΡΤΥΧ";
+
+// Ranges of code points in each script, checked in order
+const SCRIPT_RANGES: &[(u32, u32, Script)] = &[
+    (0x30, 0x39, Script::Common),
+    (0x2d, 0x2e, Script::Common),
+    (0x5f, 0x5f, Script::Common),
+    (0x41, 0x5a, Script::Latin),
+    (0x61, 0x7a, Script::Latin),
+    (0xc0, 0x24f, Script::Latin),
+    (0x1e00, 0x1eff, Script::Latin),
+    (0x370, 0x3ff, Script::Greek),
+    (0x1f00, 0x1fff, Script::Greek),
+    (0x400, 0x52f, Script::Cyrillic),
+    (0x2de0, 0x2dff, Script::Cyrillic),
+    (0xa640, 0xa69f, Script::Cyrillic),
+    (0x530, 0x58f, Script::Armenian),
+    (0x590, 0x5ff, Script::Hebrew),
+    (0x600, 0x6ff, Script::Arabic),
+    (0x750, 0x77f, Script::Arabic),
+    (0x900, 0x97f, Script::Devanagari),
+    (0xe00, 0xe7f, Script::Thai),
+    (0x10a0, 0x10ff, Script::Georgian),
+    (0x13a0, 0x13ff, Script::Cherokee),
+    (0x1100, 0x11ff, Script::Hangul),
+    (0x3130, 0x318f, Script::Hangul),
+    (0xac00, 0xd7af, Script::Hangul),
+    (0x3400, 0x4dbf, Script::Han),
+    (0x4e00, 0x9fff, Script::Han),
+    (0x3040, 0x30ff, Script::Kana),
+];
+
+// Sorted sets of scripts that Japanese and Korean domains legitimately mix
+const CJK_MIXES: &[&[Script]] = &[
+    &[Script::Hangul, Script::Han],
+    &[Script::Han, Script::Kana],
+    &[Script::Latin, Script::Han],
+    &[Script::Latin, Script::Kana],
+    &[Script::Latin, Script::Han, Script::Kana],
+];
+
+fn script(c: char) -> Script {
+    let c = c as u32;
+    SCRIPT_RANGES
+        .iter()
+        .find(|&&(start, end, _)| c >= start && c <= end)
+        .map_or(Script::Other, |&(_, _, script)| script)
+}
+
+// Check if a single Unicode domain label mixes scripts, or is written
+// entirely in a non-Latin script using only letters that look Latin
+fn suspicious_label(label: &str) -> bool {
+    let mut scripts: Vec<Script> = label
+        .chars()
+        .map(script)
+        .filter(|s| *s != Script::Common)
+        .collect();
+    scripts.sort_by_key(|s| *s as u8);
+    scripts.dedup();
+
+    if scripts.len() > 1 {
+        return !CJK_MIXES.iter().any(|mix| mix[..] == scripts[..]);
+    }
+    match scripts.first() {
+        Some(&Script::Greek) | Some(&Script::Cyrillic) => label
+            .chars()
+            .filter(|c| script(*c) != Script::Common)
+            .all(|c| LATIN_CONFUSABLES.contains(c)),
+        _ => false,
+    }
+}
+
+/// Convert a punycode domain to its Unicode form for display.
+///
+/// Labels that can not be decoded are left as they are.
+pub fn domain_to_unicode(domain: &str) -> String {
+    idna::domain_to_unicode(domain).0
+}
+
+/// Return the Unicode form of any labels in `domain` that may be homographs.
+///
+/// A label is flagged if it mixes characters from different scripts, such
+/// as Latin and Cyrillic, or if it is written entirely in Cyrillic or Greek
+/// using only characters that can be mistaken for Latin letters. Common
+/// Chinese, Japanese and Korean script combinations are not flagged.
+pub fn suspicious_labels(domain: &str) -> Vec<String> {
+    domain_to_unicode(domain)
+        .split('.')
+        .filter(|label| suspicious_label(label))
+        .map(String::from)
+        .collect()
+}
+
+// Bundled snapshot of the Public Suffix List from https://publicsuffix.org
+const PUBLIC_SUFFIX_LIST: &str = include_str!("../data/public_suffix_list.dat");
+
+// Parsed Public Suffix List rules. Wildcard rules are stored without their
+// leading `*.` and exception rules without their leading `!`.
+struct SuffixRules {
+    rules: HashSet<String>,
+    wildcards: HashSet<String>,
+    exceptions: HashSet<String>,
+}
+
+lazy_static! {
+    static ref SUFFIX_RULES: SuffixRules = SuffixRules::parse(PUBLIC_SUFFIX_LIST);
+}
+
+impl SuffixRules {
+    fn parse(list: &str) -> SuffixRules {
+        let mut rules = SuffixRules {
+            rules: HashSet::new(),
+            wildcards: HashSet::new(),
+            exceptions: HashSet::new(),
+        };
+
+        for line in list.lines() {
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule,
+                _ => continue,
+            };
+
+            // Rules for internationalized suffixes are listed in Unicode
+            let (set, rule) = if rule.starts_with("*.") {
+                (&mut rules.wildcards, &rule[2..])
+            } else if rule.starts_with('!') {
+                (&mut rules.exceptions, &rule[1..])
+            } else {
+                (&mut rules.rules, rule)
+            };
+            if let Ok(rule) = idna::domain_to_ascii(rule) {
+                set.insert(rule);
+            }
+        }
+
+        rules
+    }
+
+    // Return the number of labels at the end of `labels` that make up its
+    // public suffix, if it matches a rule in the list
+    fn suffix_len(&self, labels: &[&str]) -> Option<usize> {
+        for i in 0..labels.len() {
+            let candidate = labels[i..].join(".");
+            if self.exceptions.contains(&candidate) {
+                return Some(labels.len() - i - 1);
+            }
+            if self.rules.contains(&candidate) {
+                return Some(labels.len() - i);
+            }
+            if i + 1 < labels.len() && self.wildcards.contains(&labels[i + 1..].join(".")) {
+                return Some(labels.len() - i);
+            }
+        }
+
+        None
+    }
+}
+
+/// A domain split into its subdomain labels, registrable domain and public
+/// suffix according to the Public Suffix List
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainParts {
+    /// The registrable domain, such as `example.co.uk` for
+    /// `a.b.example.co.uk`. `None` if the domain is itself a public suffix.
+    pub registrable: Option<String>,
+    /// The public suffix, such as `co.uk` for `a.b.example.co.uk`
+    pub suffix: String,
+    /// Labels in front of the registrable domain, such as `["a", "b"]` for
+    /// `a.b.example.co.uk`
+    pub subdomains: Vec<String>,
+}
+
+/// Split a domain, or the host of a URL, at its public suffix.
+///
+/// Uses a snapshot of the Public Suffix List bundled with this crate so no
+/// network access is needed. Both the ICANN and private sections of the
+/// list are used, so `foo.blogspot.com` is its own registrable domain.
+/// Internationalized domains are returned in punycode.
+pub fn domain_parts(domain: &str) -> Result<DomainParts> {
+    let domain = match parse_host(&refang(domain))? {
+        Host::Domain(domain) => domain,
+        _ => return Err(PassiveTotalError::InvalidDomain),
+    };
+    let labels: Vec<&str> = domain.trim_right_matches('.').split('.').collect();
+    if labels.iter().any(|l| l.is_empty()) {
+        return Err(PassiveTotalError::InvalidDomain);
+    }
+
+    // The implicit `*` rule makes any unlisted TLD a public suffix
+    let suffix_len = SUFFIX_RULES.suffix_len(&labels).unwrap_or(1);
+    let suffix_start = labels.len() - suffix_len;
+    let registrable = if suffix_start > 0 {
+        Some(labels[suffix_start - 1..].join("."))
+    } else {
+        None
+    };
+    let subdomains = labels[..suffix_start.saturating_sub(1)]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+    Ok(DomainParts {
+        registrable,
+        suffix: labels[suffix_start..].join("."),
+        subdomains,
+    })
+}
+
+/// Return the registrable domain of a domain, for example `example.co.uk`
+/// for `a.b.example.co.uk`.
+///
+/// Useful for grouping hostnames returned by passive DNS or subdomain
+/// enrichment lookups. Returns `None` if the domain is invalid or is itself
+/// a public suffix.
+pub fn registrable_domain(domain: &str) -> Option<String> {
+    domain_parts(domain).ok().and_then(|parts| parts.registrable)
+}
+
+// Check if a normalized domain ends in a suffix listed in the Public Suffix
+// List, rather than only matching the implicit `*` rule
+pub fn has_known_suffix(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.trim_right_matches('.').split('.').collect();
+    labels.len() > 1 && SUFFIX_RULES.suffix_len(&labels).is_some()
+}
+
+// Format of dates in Passivetotal requests and responses, which are in UTC
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// Parse a date from a Passivetotal response. Besides `DATE_FORMAT` some
+// endpoints, such as WHOIS, return RFC 3339 dates or dates with an offset.
+pub fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, DATE_FORMAT) {
+        return Some(Utc.from_utc_datetime(&dt));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    if let Ok(dt) = DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| Utc.from_utc_datetime(&dt))
+}
+
+// Format a date the way Passivetotal does
+pub fn format_date(dt: &DateTime<Utc>) -> String {
+    dt.format(DATE_FORMAT).to_string()
+}
@@ -0,0 +1,476 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use dirs;
+use reqwest;
+use serde_json::{self, Value};
+
+use diskcache::write_atomic;
+use whoisdiff::{diff_whois, FieldChange};
+use {utils, PassiveTotal, PassiveTotalError, Query, QueryKind, Result};
+
+const STATE_FILE: &str = "passivetotal/watch-state.json";
+const DEFAULT_INTERVAL: u64 = 60 * 60;
+
+/// A change detected for a watched query
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WatchEvent {
+    /// When the change was detected, formatted like Passivetotal dates
+    pub time: String,
+    /// The watched query
+    pub query: String,
+    #[serde(flatten)]
+    pub change: WatchChange,
+}
+
+/// The kinds of change a `Watcher` detects
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchChange {
+    /// A passive DNS resolution that was not seen before
+    NewResolution { resolve: String, record_type: String },
+    /// An SSL certificate that was not seen on the IP address before
+    NewCertificate { sha1: String },
+    /// The enrichment classification changed
+    ClassificationChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// An enrichment tag that was not seen before
+    NewTag { tag: String },
+    /// The WHOIS record changed
+    WhoisChanged { changes: Vec<FieldChange> },
+    /// Re-querying one of the endpoints failed
+    Error { error: String },
+}
+
+/// Somewhere to send the events detected by a `Watcher`
+pub trait EventSink {
+    fn emit(&mut self, event: &WatchEvent) -> Result<()>;
+
+    /// Called by `Watcher::run` when `emit` fails, with the error and the
+    /// number of events that will be sent again after the next check. Does
+    /// nothing by default.
+    fn on_error(&mut self, _error: &PassiveTotalError, _undelivered: usize) {}
+}
+
+impl<S> EventSink for Box<S>
+where
+    S: EventSink + ?Sized,
+{
+    fn emit(&mut self, event: &WatchEvent) -> Result<()> {
+        (**self).emit(event)
+    }
+
+    fn on_error(&mut self, error: &PassiveTotalError, undelivered: usize) {
+        (**self).on_error(error, undelivered)
+    }
+}
+
+/// Writes events as one JSON object per line, for example to stdout or a
+/// file opened for appending
+#[derive(Debug)]
+pub struct WriterSink<W> {
+    writer: W,
+}
+
+/// Posts each event as JSON to a webhook URL
+#[derive(Debug)]
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+// What is known about a watched query. Each field is `None` until the
+// endpoint it comes from has been queried successfully, so that the first
+// results become the baseline instead of being reported as new.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueryState {
+    resolutions: Option<BTreeSet<(String, String)>>,
+    certificates: Option<BTreeSet<String>>,
+    // An empty string if the query was not classified
+    classification: Option<String>,
+    tags: Option<BTreeSet<String>>,
+    whois: Option<Value>,
+}
+
+/// Periodically re-queries a watchlist and reports what changed.
+///
+/// Domains are checked for new passive DNS resolutions, enrichment
+/// classification and tag changes and WHOIS changes. IP addresses are
+/// checked for new resolutions, classification and tag changes and new SSL
+/// certificates. Other queries are ignored.
+///
+/// The first time a query is checked its current state is recorded without
+/// reporting any events. Use `with_state_file` to keep the state between
+/// runs, otherwise it is only kept in memory.
+///
+/// Events are kept until they are delivered and the state is only saved
+/// once every event has been delivered, so if delivering fails or the
+/// process exits the changes are reported again instead of being lost.
+///
+/// ```no_run
+/// use std::io;
+/// use passivetotal_reqwest::{PassiveTotal, Query, Watcher, WriterSink};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey").with_rate_limit(30);
+/// let queries = vec![Query::parse("passivetotal.org").unwrap()];
+/// let mut watcher = Watcher::new(&pt, queries)
+///     .with_state_file(Watcher::default_state_file().unwrap())
+///     .unwrap();
+///
+/// watcher.run(&mut WriterSink::new(io::stdout())).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Watcher<'a> {
+    pt: &'a PassiveTotal,
+    queries: Vec<Query>,
+    interval: Duration,
+    state_file: Option<PathBuf>,
+    state: BTreeMap<String, QueryState>,
+    // Events returned by `poll` that have not been delivered yet
+    pending: Vec<WatchEvent>,
+}
+
+impl<W> WriterSink<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> WriterSink<W> {
+        WriterSink { writer }
+    }
+}
+
+impl<W> EventSink for WriterSink<W>
+where
+    W: Write,
+{
+    fn emit(&mut self, event: &WatchEvent) -> Result<()> {
+        let line = serde_json::to_string(event)
+            .map_err(|e| PassiveTotalError::WatchError(e.to_string()))?;
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| PassiveTotalError::WatchError(format!("Unable to write event: {}", e)))
+    }
+}
+
+impl WebhookSink {
+    pub fn new<S>(url: S) -> WebhookSink
+    where
+        S: Into<String>,
+    {
+        WebhookSink {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn emit(&mut self, event: &WatchEvent) -> Result<()> {
+        let resp = self.client.post(self.url.as_str()).json(event).send()?;
+        if resp.status().is_client_error() {
+            Err(PassiveTotalError::ClientError(resp.status()))
+        } else if resp.status().is_server_error() {
+            Err(PassiveTotalError::ServerError(resp.status()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn error_change(e: &PassiveTotalError) -> WatchChange {
+    WatchChange::Error {
+        error: e.to_string(),
+    }
+}
+
+fn state_error(path: &Path, e: &fmt::Display) -> PassiveTotalError {
+    PassiveTotalError::ArchiveError(format!("{}: {}", path.display(), e))
+}
+
+// Collect a string field from each of the `results` of a response
+fn result_strings(resp: &Value, field: &str) -> BTreeSet<String> {
+    resp.get("results")
+        .and_then(Value::as_array)
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|r| r.get(field).and_then(Value::as_str))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Report the values in `current` that have never been seen before, then add
+// them to `known`. Values that disappear and come back are not reported
+// again. Nothing is reported the first time.
+fn new_values<T, F>(
+    known: &mut Option<BTreeSet<T>>,
+    current: BTreeSet<T>,
+    event: F,
+) -> Vec<WatchChange>
+where
+    T: Ord,
+    F: Fn(&T) -> WatchChange,
+{
+    if let Some(ref mut seen) = *known {
+        let changes = current.difference(seen).map(event).collect();
+        seen.extend(current);
+        return changes;
+    }
+    *known = Some(current);
+    Vec::new()
+}
+
+impl<'a> Watcher<'a> {
+    /// Create a watcher for the given queries, checking them once an hour
+    pub fn new(pt: &'a PassiveTotal, queries: Vec<Query>) -> Watcher<'a> {
+        Watcher {
+            pt,
+            queries,
+            interval: Duration::from_secs(DEFAULT_INTERVAL),
+            state_file: None,
+            state: BTreeMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// The default state file, `passivetotal/watch-state.json` under the
+    /// user's data directory, such as `~/.local/share` on Linux
+    pub fn default_state_file() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(STATE_FILE))
+    }
+
+    /// Set how long to wait between checks of the watchlist in `run`
+    pub fn interval(mut self, interval: Duration) -> Watcher<'a> {
+        self.interval = interval;
+        self
+    }
+
+    /// Load the known state of the watched queries from `path` and save it
+    /// there once the events from each check have been delivered
+    pub fn with_state_file<P>(mut self, path: P) -> Result<Watcher<'a>>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        self.state = match File::open(&path) {
+            Ok(f) => {
+                serde_json::from_reader(BufReader::new(f)).map_err(|e| state_error(&path, &e))?
+            },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(state_error(&path, &e)),
+        };
+        self.state_file = Some(path);
+        Ok(self)
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = match self.state_file {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| state_error(dir, &e))?;
+        }
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        write_atomic(&tmp, path, &self.state).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            state_error(path, &e)
+        })
+    }
+
+    // Re-query every endpoint that applies to `query` and compare the
+    // results with its known state
+    fn check(&self, query: &Query, state: &mut QueryState) -> Vec<WatchChange> {
+        let q = query.to_string();
+        let mut changes = Vec::new();
+        let is_domain = query.kind() == QueryKind::Domain;
+
+        match self.pt.passive_dns(&q).and_then(|r| r.send()) {
+            Ok(resp) => {
+                let resolutions = resp
+                    .get("results")
+                    .and_then(Value::as_array)
+                    .map(|results| {
+                        results
+                            .iter()
+                            .filter_map(|r| {
+                                let resolve = r.get("resolve").and_then(Value::as_str);
+                                let ty = r.get("recordType").and_then(Value::as_str).unwrap_or("");
+                                resolve.map(|resolve| (ty.to_uppercase(), resolve.to_lowercase()))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                changes.extend(new_values(&mut state.resolutions, resolutions, |r| {
+                    WatchChange::NewResolution {
+                        record_type: r.0.clone(),
+                        resolve: r.1.clone(),
+                    }
+                }));
+            },
+            Err(e) => changes.push(error_change(&e)),
+        }
+
+        match self.pt.enrichment().data(&q).and_then(|r| r.send()) {
+            Ok(resp) => {
+                let classification = resp
+                    .get("classification")
+                    .and_then(Value::as_str)
+                    .unwrap_or("")
+                    .to_owned();
+                if let Some(ref old) = state.classification {
+                    if *old != classification {
+                        let some = |c: &String| if c.is_empty() { None } else { Some(c.clone()) };
+                        changes.push(WatchChange::ClassificationChanged {
+                            old: some(old),
+                            new: some(&classification),
+                        });
+                    }
+                }
+                state.classification = Some(classification);
+
+                let tags = resp
+                    .get("tags")
+                    .and_then(Value::as_array)
+                    .map(|tags| tags.iter().filter_map(Value::as_str).map(String::from).collect())
+                    .unwrap_or_default();
+                changes.extend(new_values(&mut state.tags, tags, |tag| WatchChange::NewTag {
+                    tag: tag.clone(),
+                }));
+            },
+            Err(e) => changes.push(error_change(&e)),
+        }
+
+        if is_domain {
+            match self.pt.whois().information(&q).and_then(|r| r.send()) {
+                Ok(whois) => {
+                    if let Some(ref old) = state.whois {
+                        let diff = diff_whois(old, &whois);
+                        if !diff.is_empty() {
+                            changes.push(WatchChange::WhoisChanged { changes: diff });
+                        }
+                    }
+                    state.whois = Some(whois);
+                },
+                Err(e) => changes.push(error_change(&e)),
+            }
+        } else {
+            match self.pt.ssl().history(&q).and_then(|r| r.send()) {
+                Ok(resp) => {
+                    let certificates = result_strings(&resp, "sha1");
+                    changes.extend(new_values(&mut state.certificates, certificates, |sha1| {
+                        WatchChange::NewCertificate { sha1: sha1.clone() }
+                    }));
+                },
+                Err(e) => changes.push(error_change(&e)),
+            }
+        }
+
+        changes
+    }
+
+    /// Check every query on the watchlist once and return the changes
+    /// found, after any events from earlier checks that have not been
+    /// marked as delivered with `delivered`.
+    ///
+    /// The state is saved if a state file is set and there are no events
+    /// to deliver.
+    pub fn poll(&mut self) -> Result<Vec<WatchEvent>> {
+        let mut events = Vec::new();
+
+        for query in &self.queries {
+            match query.kind() {
+                QueryKind::Domain | QueryKind::Ipv4 | QueryKind::Ipv6 => {},
+                _ => continue,
+            }
+
+            let key = query.to_string();
+            let mut state = self.state.remove(&key).unwrap_or_default();
+            let changes = self.check(query, &mut state);
+            self.state.insert(key.clone(), state);
+
+            let time = utils::format_date(&Utc::now());
+            events.extend(changes.into_iter().map(|change| WatchEvent {
+                time: time.clone(),
+                query: key.clone(),
+                change,
+            }));
+        }
+
+        self.pending.extend(events);
+        if self.pending.is_empty() {
+            self.save()?;
+        }
+        Ok(self.pending.clone())
+    }
+
+    /// Mark the first `count` events returned by `poll` as delivered, so
+    /// they are not returned again. The state is saved once every event
+    /// has been delivered.
+    pub fn delivered(&mut self, count: usize) -> Result<()> {
+        let count = cmp::min(count, self.pending.len());
+        self.pending.drain(..count);
+        if self.pending.is_empty() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Send the events that have not been delivered yet to `sink`, in the
+    /// order they were found. Stops at the first event `sink` fails to
+    /// emit, which is sent again by the next call.
+    pub fn deliver<S>(&mut self, sink: &mut S) -> Result<()>
+    where
+        S: EventSink,
+    {
+        let mut sent = 0;
+        let mut result = Ok(());
+        for event in &self.pending {
+            result = sink.emit(event);
+            if result.is_err() {
+                break;
+            }
+            sent += 1;
+        }
+        self.delivered(sent)?;
+        result
+    }
+
+    /// Check the watchlist every `interval` forever, sending the changes to
+    /// `sink`.
+    ///
+    /// Events `sink` fails to emit are passed to its `on_error` method and
+    /// sent again after the next check. Only returns if saving the state
+    /// fails.
+    pub fn run<S>(&mut self, sink: &mut S) -> Result<()>
+    where
+        S: EventSink,
+    {
+        loop {
+            let events = self.poll()?;
+            let mut sent = 0;
+            for event in &events {
+                if let Err(e) = sink.emit(event) {
+                    sink.on_error(&e, events.len() - sent);
+                    break;
+                }
+                sent += 1;
+            }
+            self.delivered(sent)?;
+            thread::sleep(self.interval);
+        }
+    }
+}
@@ -3,7 +3,8 @@ use std::str::FromStr;
 use serde::{Serialize, Serializer};
 use serde_json::Value;
 
-use {PassiveTotal, PassiveTotalError, Result};
+use query::HOST_KINDS;
+use {PassiveTotal, PassiveTotalError, Query, QueryKind, Result};
 
 const URL_WHOIS: &str = "/whois";
 const URL_WHOIS_KEYWORD: &str = "/whois/search/keyword";
@@ -14,16 +15,20 @@ pub struct WhoisRequest<'a> {
 }
 
 request_struct!(WhoisInfo {
-    query: &'a str,
+    query: Query,
 });
 
 request_struct!(WhoisSearchField {
-    query: &'a str,
+    query: String,
     field: WhoisField,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
 });
 
 request_struct!(WhoisSearchKeyword {
     query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
 });
 
 /// Represents the available WHOIS search fields for WHOIS field searches
@@ -51,6 +56,16 @@ impl WhoisField {
             WhoisField::Nameserver => "nameserver",
         }
     }
+
+    // Returns the `QueryKind` a query must be for searches on this field, if
+    // the field is not free text
+    fn query_kind(&self) -> Option<QueryKind> {
+        match *self {
+            WhoisField::Email => Some(QueryKind::Email),
+            WhoisField::Domain | WhoisField::Nameserver => Some(QueryKind::Domain),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for WhoisField {
@@ -81,21 +96,27 @@ impl Serialize for WhoisField {
 }
 
 impl<'a> WhoisRequest<'a> {
-    pub fn information(self, query: &'a str) -> WhoisInfo {
-        WhoisInfo {
+    pub fn information(self, query: &str) -> Result<WhoisInfo<'a>> {
+        Ok(WhoisInfo {
             pt: self.pt,
             url: URL_WHOIS,
-            query,
-        }
+            query: Query::parse_any(query, HOST_KINDS)?,
+        })
     }
 
-    pub fn search_field(self, query: &'a str, field: WhoisField) -> WhoisSearchField {
-        WhoisSearchField {
+    pub fn search_field(self, query: &str, field: WhoisField) -> Result<WhoisSearchField<'a>> {
+        let query = match field.query_kind() {
+            Some(kind) => Query::parse_as(query, kind)?.to_string(),
+            None => String::from(query),
+        };
+
+        Ok(WhoisSearchField {
             pt: self.pt,
             url: URL_WHOIS_SEARCH,
             query,
             field,
-        }
+            page: None,
+        })
     }
 
     pub fn search_keyword(self, query: &'a str) -> WhoisSearchKeyword {
@@ -103,6 +124,7 @@ impl<'a> WhoisRequest<'a> {
             pt: self.pt,
             url: URL_WHOIS_KEYWORD,
             query,
+            page: None,
         }
     }
 }
@@ -111,6 +133,9 @@ impl_send!(WhoisInfo);
 impl_send!(WhoisSearchField);
 impl_send!(WhoisSearchKeyword);
 
+impl_paged!(WhoisSearchField);
+impl_paged!(WhoisSearchKeyword);
+
 impl PassiveTotal {
     pub fn whois(&self) -> WhoisRequest {
         WhoisRequest { pt: self }
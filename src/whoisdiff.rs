@@ -0,0 +1,295 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{DateTime, Utc};
+use dirs;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{self, Map, Value};
+
+use diskcache::write_atomic;
+use {utils, PassiveTotalError, Query, QueryKind, Result};
+
+const STORE_DIR: &str = "passivetotal/whois";
+
+// Fields that change on every lookup without the registration changing
+const IGNORED_FIELDS: &[&str] = &["lastLoadedAt", "rawText"];
+
+/// How a WHOIS field changed between two snapshots
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A change to a single WHOIS field
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// Path of the field, with contact sub-fields separated by `.`, such as
+    /// `registrant.email`
+    pub field: String,
+    pub change: ChangeKind,
+    /// The previous value, `None` if the field was added
+    pub old: Option<Value>,
+    /// The new value, `None` if the field was removed
+    pub new: Option<Value>,
+}
+
+/// A WHOIS record stored in a `WhoisStore`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WhoisSnapshot {
+    /// When the record was fetched, stored formatted like Passivetotal
+    /// dates
+    #[serde(serialize_with = "serialize_date", deserialize_with = "deserialize_date")]
+    pub fetched: DateTime<Utc>,
+    /// The `whois().information()` response
+    pub whois: Value,
+}
+
+/// Stores WHOIS records for domains over time so that changes to their
+/// registration can be detected.
+///
+/// Snapshots are kept in one file per domain and a new snapshot is only
+/// stored when the record differs from the latest one.
+///
+/// ```no_run
+/// use passivetotal_reqwest::{PassiveTotal, WhoisStore};
+///
+/// let pt = PassiveTotal::with_auth("username", "apikey");
+/// let store = WhoisStore::open(WhoisStore::default_dir().unwrap()).unwrap();
+///
+/// let whois = pt.whois().information("passivetotal.org").unwrap().send().unwrap();
+/// for change in store.add("passivetotal.org", &whois).unwrap() {
+///     println!("{:?} {}: {:?} -> {:?}", change.change, change.field, change.old, change.new);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WhoisStore {
+    dir: PathBuf,
+}
+
+impl ChangeKind {
+    /// Returns a `&str` representation of a `ChangeKind` enum
+    pub fn as_str(&self) -> &str {
+        match *self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ChangeKind {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+// Empty strings, arrays and objects are how Passivetotal reports a field it
+// has no value for, so they are treated the same as a missing field
+fn is_empty(value: &Value) -> bool {
+    match *value {
+        Value::Null => true,
+        Value::String(ref s) => s.trim().is_empty(),
+        Value::Array(ref a) => a.is_empty(),
+        Value::Object(ref o) => o.values().all(is_empty),
+        _ => false,
+    }
+}
+
+// Lists such as name servers are compared ignoring order and case
+fn normalize(value: &Value) -> Value {
+    match *value {
+        Value::Array(ref items) => {
+            let mut items: Vec<Value> = items
+                .iter()
+                .map(|v| match *v {
+                    Value::String(ref s) => Value::String(s.trim().to_lowercase()),
+                    ref v => normalize(v),
+                })
+                .collect();
+            items.sort_by_key(|v| v.to_string());
+            items.dedup();
+            Value::Array(items)
+        },
+        Value::String(ref s) => Value::String(s.trim().to_owned()),
+        ref v => v.clone(),
+    }
+}
+
+fn diff_fields(
+    prefix: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    out: &mut Vec<FieldChange>,
+) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        if prefix.is_empty() && IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+
+        let field = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        let old = old.get(key).filter(|v| !is_empty(v));
+        let new = new.get(key).filter(|v| !is_empty(v));
+
+        match (old, new) {
+            (Some(Value::Object(o)), Some(Value::Object(n))) => {
+                diff_fields(&field, o, n, out);
+            },
+            (Some(o), Some(n)) => {
+                if normalize(o) != normalize(n) {
+                    out.push(FieldChange {
+                        field,
+                        change: ChangeKind::Changed,
+                        old: Some(o.clone()),
+                        new: Some(n.clone()),
+                    });
+                }
+            },
+            (Some(o), None) => out.push(FieldChange {
+                field,
+                change: ChangeKind::Removed,
+                old: Some(o.clone()),
+                new: None,
+            }),
+            (None, Some(n)) => out.push(FieldChange {
+                field,
+                change: ChangeKind::Added,
+                old: None,
+                new: Some(n.clone()),
+            }),
+            (None, None) => {},
+        }
+    }
+}
+
+/// Compare two `whois().information()` responses field by field.
+///
+/// Contact records such as `registrant` are compared sub-field by
+/// sub-field, lists such as `nameServers` are compared ignoring order and
+/// case, and empty values are treated as missing. Fields that change on
+/// every lookup, `lastLoadedAt` and `rawText`, are ignored. Changes are
+/// ordered by field.
+pub fn diff_whois(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let empty = Map::new();
+    let mut changes = Vec::new();
+    diff_fields(
+        "",
+        old.as_object().unwrap_or(&empty),
+        new.as_object().unwrap_or(&empty),
+        &mut changes,
+    );
+    changes
+}
+
+fn serialize_date<S>(dt: &DateTime<Utc>, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&utils::format_date(dt))
+}
+
+fn deserialize_date<'de, D>(deserializer: D) -> ::std::result::Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    utils::parse_date(&s).ok_or_else(|| D::Error::custom(format!("invalid date: {}", s)))
+}
+
+fn store_error(path: &Path, e: &fmt::Display) -> PassiveTotalError {
+    PassiveTotalError::ArchiveError(format!("{}: {}", path.display(), e))
+}
+
+impl WhoisStore {
+    /// The default store directory, `passivetotal/whois` under the user's
+    /// data directory, such as `~/.local/share` on Linux
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(STORE_DIR))
+    }
+
+    /// Open the store in `dir`, creating the directory if needed
+    pub fn open<P>(dir: P) -> Result<WhoisStore>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| store_error(&dir, &e))?;
+        Ok(WhoisStore { dir })
+    }
+
+    // Snapshots are stored under the normalized domain, which only
+    // contains characters that are safe in file names
+    fn path(&self, domain: &str) -> Result<PathBuf> {
+        let domain = Query::parse_as(domain, QueryKind::Domain)?;
+        Ok(self.dir.join(format!("{}.json", domain)))
+    }
+
+    /// Returns the stored snapshots for a domain, oldest first
+    pub fn snapshots(&self, domain: &str) -> Result<Vec<WhoisSnapshot>> {
+        let path = self.path(domain)?;
+        match File::open(&path) {
+            Ok(f) => {
+                serde_json::from_reader(BufReader::new(f)).map_err(|e| store_error(&path, &e))
+            },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(store_error(&path, &e)),
+        }
+    }
+
+    /// Returns the most recent snapshot for a domain
+    pub fn latest(&self, domain: &str) -> Result<Option<WhoisSnapshot>> {
+        Ok(self.snapshots(domain)?.pop())
+    }
+
+    /// Compare a WHOIS record for a domain with its latest snapshot and
+    /// store it if it changed. Returns the changes, which are empty if the
+    /// record is unchanged or is the first one stored for the domain.
+    pub fn add(&self, domain: &str, whois: &Value) -> Result<Vec<FieldChange>> {
+        let path = self.path(domain)?;
+        let mut snapshots = self.snapshots(domain)?;
+
+        let changes = match snapshots.last() {
+            Some(latest) => {
+                let changes = diff_whois(&latest.whois, whois);
+                if changes.is_empty() {
+                    return Ok(changes);
+                }
+                changes
+            },
+            None => Vec::new(),
+        };
+
+        snapshots.push(WhoisSnapshot {
+            fetched: Utc::now(),
+            whois: whois.clone(),
+        });
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        write_atomic(&tmp, &path, &snapshots).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            store_error(&path, &e)
+        })?;
+        Ok(changes)
+    }
+}
@@ -0,0 +1,110 @@
+extern crate chrono;
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use passivetotal_reqwest::PdnsArchive;
+
+fn archive_path(name: &str) -> PathBuf {
+    let name = format!("passivetotal-archive-{}-{}.json", name, std::process::id());
+    let path = env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn date(s: &str) -> Option<DateTime<Utc>> {
+    s.parse().ok()
+}
+
+fn response(source: &str, first: &str, last: &str) -> serde_json::Value {
+    json!({
+        "results": [{
+            "resolve": "1.2.3.4",
+            "recordType": "A",
+            "source": [source],
+            "firstSeen": first,
+            "lastSeen": last,
+        }]
+    })
+}
+
+#[test]
+fn merges_observations() {
+    let mut archive = PdnsArchive::new();
+    let first = response("riskiq", "2018-01-01 00:00:00", "2018-02-01 00:00:00");
+    let second = response("kaspersky", "2017-06-01 00:00:00", "2018-01-15 00:00:00");
+
+    assert_eq!(archive.add_response("example.com", &first), 1);
+    assert_eq!(archive.add_response("example.com", &second), 1);
+    assert_eq!(archive.len(), 1);
+
+    let record = archive.lookup("example.com")[0];
+    let sources: Vec<_> = record.sources.iter().map(String::as_str).collect();
+    assert_eq!(sources, vec!["kaspersky", "riskiq"]);
+    assert_eq!(record.first_seen, date("2017-06-01T00:00:00Z"));
+    assert_eq!(record.last_seen, date("2018-02-01T00:00:00Z"));
+}
+
+#[test]
+fn dedups_resolutions() {
+    let mut archive = PdnsArchive::new();
+    let resp = response("riskiq", "2018-01-01 00:00:00", "2018-02-01 00:00:00");
+
+    assert_eq!(archive.add_response("example.com", &resp), 1);
+    assert_eq!(archive.add_response("example.com", &resp), 0);
+    // The same domain looked up as a URL or in another case
+    assert_eq!(archive.add_response("https://EXAMPLE.com/path", &resp), 0);
+    assert_eq!(archive.len(), 1);
+
+    // A different record type is a different resolution
+    let mut cname = resp.clone();
+    cname["results"][0]["recordType"] = json!("cname");
+    assert_eq!(archive.add_response("example.com", &cname), 1);
+    assert_eq!(archive.len(), 2);
+    assert_eq!(archive.lookup("1.2.3.4").len(), 2);
+}
+
+#[test]
+fn save_merges_with_other_processes() {
+    let path = archive_path("save");
+    let mut first = PdnsArchive::open(&path).unwrap();
+    let mut second = PdnsArchive::open(&path).unwrap();
+
+    let resp = response("riskiq", "2018-01-01 00:00:00", "2018-01-01 00:00:00");
+    first.add_response("example.com", &resp);
+    second.add_response("example.org", &resp);
+    first.save().unwrap();
+    second.save().unwrap();
+
+    let archive = PdnsArchive::open(&path).unwrap();
+    assert_eq!(archive.len(), 2);
+    assert!(!path.with_extension("lock").exists());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn invalid_dates_are_errors() {
+    let path = archive_path("dates");
+    let records = json!([{
+        "query": "example.com",
+        "resolve": "1.2.3.4",
+        "recordType": "A",
+        "sources": [],
+        "firstSeen": "yesterday",
+        "lastSeen": null,
+    }]);
+    File::create(&path)
+        .and_then(|mut f| f.write_all(records.to_string().as_bytes()))
+        .unwrap();
+
+    assert!(PdnsArchive::open(&path).is_err());
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,111 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use std::thread;
+use std::time::Duration;
+
+use passivetotal_reqwest::{Batch, PassiveTotalError, Request, Result};
+use serde_json::Value;
+
+// Responds with its number after a delay, failing for odd numbers
+struct Delayed(u64);
+
+impl Request for Delayed {
+    fn send(&self) -> Result<Value> {
+        thread::sleep(Duration::from_millis(10 * (5 - self.0 % 5)));
+        if self.0 % 2 == 1 {
+            return Err(PassiveTotalError::ConfigError(format!("odd {}", self.0)));
+        }
+        Ok(json!(self.0))
+    }
+}
+
+fn keyed(n: u64) -> Batch<Delayed, String> {
+    Batch::keyed((0..n).map(|i| (format!("query-{}", i), Delayed(i))))
+}
+
+#[test]
+fn iterates_in_order_with_keys() {
+    let results: Vec<_> = keyed(10).workers(3).into_iter().collect();
+
+    assert_eq!(results.len(), 10);
+    for (i, (key, result)) in results.into_iter().enumerate() {
+        assert_eq!(key, format!("query-{}", i));
+        match result {
+            Ok(value) => assert_eq!(value, json!(i)),
+            Err(_) => assert_eq!(i % 2, 1),
+        }
+    }
+}
+
+#[test]
+fn iterates_as_completed() {
+    let mut keys: Vec<_> = keyed(10)
+        .workers(4)
+        .in_order(false)
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+
+    let mut expected: Vec<_> = (0..10).map(|i| format!("query-{}", i)).collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn iterator_is_lazy() {
+    let mut iter = keyed(10).workers(2).into_iter();
+    assert_eq!(iter.size_hint(), (10, Some(10)));
+
+    assert_eq!(iter.next().map(|(key, _)| key), Some(String::from("query-0")));
+    assert_eq!(iter.size_hint(), (9, Some(9)));
+    assert_eq!(iter.count(), 9);
+}
+
+#[test]
+fn collect_by_index() {
+    let results = Batch::new((0..6).map(Delayed)).workers(6).collect();
+    let indices: Vec<_> = results.iter().map(|&(i, _)| i).collect();
+    assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+    assert!(results[1].1.is_err());
+    assert_eq!(results[4].1.as_ref().ok(), Some(&json!(4)));
+}
+
+// Borrows its response, like request builders borrow their client
+struct Borrowed<'a>(&'a Value);
+
+impl<'a> Request for Borrowed<'a> {
+    fn send(&self) -> Result<Value> {
+        Ok(self.0.clone())
+    }
+}
+
+#[test]
+fn requests_borrow_local_data() {
+    let responses: Vec<Value> = (0..5).map(|i| json!(i)).collect();
+    let mut received = Vec::new();
+
+    Batch::new(responses.iter().map(Borrowed))
+        .workers(3)
+        .for_each(|i, result| received.push((i, result.unwrap())));
+    received.sort_by_key(|&(i, _)| i);
+
+    let expected: Vec<_> = responses.into_iter().enumerate().collect();
+    assert_eq!(received, expected);
+}
+
+struct Panics;
+
+impl Request for Panics {
+    fn send(&self) -> Result<Value> {
+        panic!("request panicked");
+    }
+}
+
+#[test]
+#[should_panic(expected = "a batch worker panicked")]
+fn worker_panics_are_raised() {
+    Batch::new(vec![Panics, Panics]).workers(2).for_each(|_, _| {});
+}
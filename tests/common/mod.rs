@@ -0,0 +1,88 @@
+// A local HTTP server standing in for the Passivetotal API. Not every test
+// uses all of it.
+#![allow(dead_code)]
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request received by the server
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub path: String,
+    pub authorization: String,
+    pub body: String,
+}
+
+/// Start a server answering every request with the status and JSON body
+/// returned by `respond`. Returns its base URL and the requests received so
+/// far.
+pub fn serve<F>(respond: F) -> (String, Arc<Mutex<Vec<Received>>>)
+where
+    F: Fn(&Received) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let request = match read_request(&mut BufReader::new(&stream)) {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+            let (status, body) = respond(&request);
+            log.lock().unwrap().push(request);
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Status\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+
+    (url, received)
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Received> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let path = line.split_whitespace().nth(1).unwrap_or("").to_owned();
+
+    let mut authorization = String::new();
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or("").to_lowercase();
+        let value = parts.next().unwrap_or("").trim();
+        if name == "authorization" {
+            authorization = value.to_owned();
+        } else if name == "content-length" {
+            length = value.parse().unwrap_or(0);
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Received {
+        path,
+        authorization,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
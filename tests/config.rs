@@ -0,0 +1,97 @@
+extern crate passivetotal_reqwest;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use passivetotal_reqwest::Config;
+
+const CONFIG: &str = r#"
+[passivetotal]
+username = "default-user"
+apikey = "default-key"
+
+[profile.automation]
+username = "automation-user"
+apikey = "automation-key"
+timeout = 30
+
+[profile.reporting]
+username = "reporting-user"
+apikey = "reporting-key"
+base_url = "https://proxy.example.com/v2"
+rate_limit = 10
+"#;
+
+fn write_config(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("passivetotal-{}-{}.toml", name, std::process::id()));
+    File::create(&path)
+        .and_then(|mut f| f.write_all(CONFIG.as_bytes()))
+        .unwrap();
+    path
+}
+
+// The environment is shared by every test in this file, so all of the
+// precedence checks run in a single test.
+#[test]
+fn credential_precedence() {
+    let path = write_config("precedence");
+
+    env::remove_var("PT_USERNAME");
+    env::remove_var("PT_APIKEY");
+    env::remove_var("PT_TIMEOUT");
+
+    let config = Config::load_profile(Some(&path), None).unwrap();
+    assert_eq!(config.username, "default-user");
+    assert_eq!(config.apikey.expose(), "default-key");
+
+    // Both credentials from the environment replace the default profile
+    env::set_var("PT_USERNAME", "env-user");
+    env::set_var("PT_APIKEY", "env-key");
+    let config = Config::load_profile(Some(&path), None).unwrap();
+    assert_eq!(config.username, "env-user");
+    assert_eq!(config.apikey.expose(), "env-key");
+
+    // A named profile keeps its own credentials but takes the timeout
+    env::set_var("PT_TIMEOUT", "5");
+    let config = Config::load_profile(Some(&path), Some("automation")).unwrap();
+    assert_eq!(config.username, "automation-user");
+    assert_eq!(config.apikey.expose(), "automation-key");
+    assert_eq!(config.timeout, Duration::from_secs(5));
+    env::remove_var("PT_TIMEOUT");
+
+    // A username alone is never paired with a key from the file
+    env::remove_var("PT_APIKEY");
+    assert!(Config::load_profile(Some(&path), None).is_err());
+    let config = Config::load_profile(Some(&path), Some("automation")).unwrap();
+    assert_eq!(config.username, "automation-user");
+    assert_eq!(config.apikey.expose(), "automation-key");
+
+    env::remove_var("PT_USERNAME");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn named_profile() {
+    let path = write_config("named");
+
+    let config = Config::load_profile(Some(&path), Some("reporting")).unwrap();
+    assert_eq!(config.username, "reporting-user");
+    assert_eq!(config.apikey.expose(), "reporting-key");
+    assert_eq!(config.base_url, "https://proxy.example.com/v2");
+    assert_eq!(config.rate_limit, Some(10));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn missing_profile() {
+    let path = write_config("missing");
+
+    let err = Config::load_profile(Some(&path), Some("nope")).unwrap_err();
+    assert!(err.to_string().contains("No profile nope"));
+
+    fs::remove_file(&path).unwrap();
+}
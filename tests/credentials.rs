@@ -0,0 +1,137 @@
+extern crate passivetotal_reqwest;
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use passivetotal_reqwest::{
+    CredentialProvider, Credentials, EnvCredentials, FileCredentials, PassiveTotal,
+    PassiveTotalError, Result,
+};
+
+#[cfg(unix)]
+use passivetotal_reqwest::CommandCredentials;
+
+// Basic auth for `user:new-key`
+const NEW_AUTH: &str = "Basic dXNlcjpuZXcta2V5";
+
+fn apikey_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("passivetotal-key-{}-{}", name, std::process::id()));
+    File::create(&path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .unwrap();
+    path
+}
+
+// Returns `old-key` the first time it is asked and `new-key` afterwards, as
+// if the key was rotated after the client started
+#[derive(Debug, Default)]
+struct RotatingCredentials {
+    calls: AtomicUsize,
+}
+
+impl CredentialProvider for RotatingCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        match self.calls.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(Credentials::new("user", "old-key")),
+            _ => Ok(Credentials::new("user", "new-key")),
+        }
+    }
+}
+
+#[test]
+fn env_credentials() {
+    // Variables unique to this test as the environment is shared
+    env::set_var("PT_TEST_ENV_USERNAME", "env-user");
+    env::set_var("PT_TEST_ENV_APIKEY", "env-key");
+    let provider = EnvCredentials::with_vars("PT_TEST_ENV_USERNAME", "PT_TEST_ENV_APIKEY");
+
+    let creds = provider.credentials().unwrap();
+    assert_eq!(creds.username, "env-user");
+    assert_eq!(creds.apikey.expose(), "env-key");
+
+    env::remove_var("PT_TEST_ENV_APIKEY");
+    let err = provider.credentials().unwrap_err();
+    assert!(err.to_string().contains("PT_TEST_ENV_APIKEY is not set"));
+}
+
+#[test]
+fn file_credentials() {
+    let path = apikey_file("file", "  file-key\n");
+    let creds = FileCredentials::new("file-user", &path).credentials().unwrap();
+    assert_eq!(creds.username, "file-user");
+    assert_eq!(creds.apikey.expose(), "file-key");
+    fs::remove_file(&path).unwrap();
+
+    let empty = apikey_file("empty", "\n");
+    assert!(FileCredentials::new("file-user", &empty).credentials().is_err());
+    fs::remove_file(&empty).unwrap();
+
+    // The file is gone
+    assert!(FileCredentials::new("file-user", &path).credentials().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn command_credentials() {
+    let creds = CommandCredentials::new("sh")
+        .arg("-c")
+        .arg("echo username=cmd-user; echo 'apikey= cmd-key '")
+        .credentials()
+        .unwrap();
+    assert_eq!(creds.username, "cmd-user");
+    assert_eq!(creds.apikey.expose(), "cmd-key");
+
+    let err = CommandCredentials::new("sh")
+        .arg("-c")
+        .arg("echo username=cmd-user")
+        .credentials()
+        .unwrap_err();
+    assert!(err.to_string().contains("did not print a username and apikey"));
+
+    assert!(CommandCredentials::new("false").credentials().is_err());
+}
+
+#[test]
+fn retries_once_with_rotated_key() {
+    let (url, received) = common::serve(|request| {
+        if request.authorization == NEW_AUTH {
+            (200, String::from(r#"{"results": []}"#))
+        } else {
+            (401, String::from("{}"))
+        }
+    });
+    let pt = PassiveTotal::with_credential_provider(
+        RotatingCredentials::default(),
+        Duration::from_secs(10),
+    ).with_base_url(url);
+
+    assert!(pt.passive_dns("example.com").unwrap().send().is_ok());
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|r| r.path == "/dns/passive"));
+        assert_eq!(received[1].authorization, NEW_AUTH);
+    }
+
+    // The new key is kept for later requests
+    assert!(pt.passive_dns("example.org").unwrap().send().is_ok());
+    assert_eq!(received.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn unchanged_key_is_not_retried() {
+    let (url, received) = common::serve(|_| (401, String::from("{}")));
+    let pt = PassiveTotal::with_auth("user", "old-key").with_base_url(url);
+
+    match pt.passive_dns("example.com").unwrap().send() {
+        Err(PassiveTotalError::ClientError(status)) => assert_eq!(status.as_u16(), 401),
+        other => panic!("expected a ClientError, got {:?}", other),
+    }
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
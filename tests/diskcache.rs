@@ -0,0 +1,69 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use passivetotal_reqwest::DiskCache;
+
+const ACCOUNT: &str = "user https://api.passivetotal.org/v2";
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("passivetotal-cache-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn stores_responses_per_account() {
+    let dir = cache_dir("account");
+    let cache = DiskCache::open(&dir).unwrap();
+    let response = json!({"results": ["1.2.3.4"]});
+    let params = r#"{"query":"example.com"}"#;
+
+    cache.insert(ACCOUNT, "/dns/passive", params, &response).unwrap();
+    assert_eq!(cache.get(ACCOUNT, "/dns/passive", params), Some(response.clone()));
+    assert_eq!(cache.get_stale(ACCOUNT, "/dns/passive", params), Some(response));
+
+    // Another user or API never sees the response
+    assert_eq!(cache.get("other https://api.passivetotal.org/v2", "/dns/passive", params), None);
+    assert_eq!(cache.get("user https://proxy.example.com/v2", "/dns/passive", params), None);
+    assert_eq!(cache.get(ACCOUNT, "/dns/passive", r#"{"query":"example.org"}"#), None);
+
+    let entries = cache.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].account, ACCOUNT);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn skips_uncached_endpoints() {
+    let dir = cache_dir("quota");
+    let cache = DiskCache::open(&dir).unwrap();
+
+    cache.insert(ACCOUNT, "/account/quota", "{}", &json!({})).unwrap();
+    assert_eq!(cache.get_stale(ACCOUNT, "/account/quota", "{}"), None);
+    assert!(cache.entries().unwrap().is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = cache_dir("mode");
+    let cache = DiskCache::open(&dir).unwrap();
+    cache.insert(ACCOUNT, "/whois", "{}", &json!({})).unwrap();
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let mode = entry.unwrap().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
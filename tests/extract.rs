@@ -0,0 +1,101 @@
+extern crate passivetotal_reqwest;
+
+use passivetotal_reqwest::{extract_indicators, extract_queries, refang, Query, QueryKind};
+
+#[test]
+fn refang_only_leading_scheme() {
+    assert_eq!(refang("hxxp://evil[.]com"), "http://evil.com");
+    assert_eq!(refang("see hxxp://evil[.]com"), "see hxxp://evil.com");
+}
+
+#[test]
+fn defanged_url_in_text() {
+    let found = extract_indicators("Beacons to hxxps://evil[.]com/gate.php, then 1.2.3[.]4.");
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].query, Query::Domain(String::from("evil.com")));
+    assert_eq!(found[0].url, Some(String::from("https://evil.com/gate.php")));
+    assert_eq!(found[1].query.to_string(), "1.2.3.4");
+    assert_eq!(found[1].url, None);
+}
+
+fn queries(text: &str) -> Vec<String> {
+    extract_queries(text).iter().map(|q| q.to_string()).collect()
+}
+
+#[test]
+fn email_addresses() {
+    let found = extract_queries("Sent from admin@evil.com to victim@example.org");
+    assert_eq!(
+        found,
+        vec![
+            Query::Email(String::from("admin@evil.com")),
+            Query::Email(String::from("victim@example.org")),
+        ]
+    );
+}
+
+#[test]
+fn hashes() {
+    let md5 = "d41d8cd98f00b204e9800998ecf8427e";
+    let sha1 = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+    let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let text = format!("MD5 {}, SHA-1 {} and SHA-256 {}", md5, sha1, sha256);
+
+    assert_eq!(
+        extract_queries(&text),
+        vec![
+            Query::Hash(String::from(md5)),
+            Query::Hash(String::from(sha1)),
+            Query::Hash(String::from(sha256)),
+        ]
+    );
+    // Hex strings that are part of a longer word are not hashes
+    assert!(extract_queries(&format!("x{}", md5)).is_empty());
+}
+
+#[test]
+fn certificate_fingerprints() {
+    let fingerprint = "DA:39:A3:EE:5E:6B:4B:0D:32:55:BF:EF:95:60:18:90:AF:D8:07:09";
+    let found = extract_queries(&format!("Certificate {} seen", fingerprint));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].kind(), QueryKind::Certificate);
+}
+
+#[test]
+fn ipv6_addresses() {
+    assert_eq!(
+        queries("Connections from 2001:db8::1 and [2001:DB8:0:0:0:0:0:2]."),
+        vec!["2001:db8::1", "2001:db8::2"]
+    );
+}
+
+#[test]
+fn defanged_indicators() {
+    assert_eq!(
+        queries("Contact bad[@]evil[.]com or visit evil[.]org from 10[.]0[.]0[.]1"),
+        vec!["bad@evil.com", "10.0.0.1", "evil.org"]
+    );
+}
+
+#[test]
+fn duplicates_removed() {
+    assert_eq!(
+        queries("evil.com resolved to 1.2.3.4; EVIL.com resolved to 1.2.3.4 again"),
+        vec!["1.2.3.4", "evil.com"]
+    );
+}
+
+#[test]
+fn unknown_suffixes_skipped() {
+    assert_eq!(queries("Opened invoice.exe and report.pdf from evil.com"), vec!["evil.com"]);
+}
+
+#[test]
+fn unparsed_matches_keep_text() {
+    // Timestamps and MAC addresses match the IPv6 pattern but are not
+    // addresses, so the indicators next to them are still found
+    assert_eq!(
+        queries("At 12:34:56:1.2.3.4 from 00:11:22:33:44:55 evil.com"),
+        vec!["1.2.3.4", "evil.com"]
+    );
+}
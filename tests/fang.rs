@@ -0,0 +1,72 @@
+extern crate passivetotal_reqwest;
+
+use passivetotal_reqwest::{defang, refang, Query, QueryKind};
+
+const INDICATORS: &[&str] = &[
+    "evil.com",
+    "1.2.3.4",
+    "user@example.com",
+    "https://bad.site/path?a=b",
+    "http://www.evil.com/",
+    "ftp://files.evil.com/x.exe",
+];
+
+#[test]
+fn refang_separators() {
+    assert_eq!(refang("evil[.]com"), "evil.com");
+    assert_eq!(refang("evil(dot)com"), "evil.com");
+    assert_eq!(refang("1.2.3[.]4"), "1.2.3.4");
+    assert_eq!(refang("user[at]example(dot)com"), "user@example.com");
+    assert_eq!(refang("user[@]example{.}com"), "user@example.com");
+    assert_eq!(refang("evil[DOT]com"), "evil.com");
+}
+
+#[test]
+fn refang_schemes() {
+    assert_eq!(refang("hxxps://bad[.]site/path"), "https://bad.site/path");
+    assert_eq!(refang("HXXP://bad[.]site"), "http://bad.site");
+    assert_eq!(refang("h**ps[://]bad[.]site"), "https://bad.site");
+    assert_eq!(refang("fxp://files[.]evil[.]com"), "ftp://files.evil.com");
+}
+
+#[test]
+fn refang_unchanged() {
+    for indicator in INDICATORS {
+        assert_eq!(refang(indicator), *indicator);
+    }
+}
+
+#[test]
+fn defang_indicators() {
+    assert_eq!(defang("evil.com"), "evil[.]com");
+    assert_eq!(defang("user@example.com"), "user[@]example[.]com");
+    assert_eq!(defang("https://bad.site/path"), "hxxps://bad[.]site/path");
+    assert_eq!(defang("ftp://files.evil.com"), "fxp://files[.]evil[.]com");
+}
+
+#[test]
+fn defang_round_trip() {
+    for indicator in INDICATORS {
+        assert_eq!(refang(&defang(indicator)), *indicator);
+    }
+}
+
+#[test]
+fn defang_idempotent() {
+    for indicator in INDICATORS {
+        let defanged = defang(indicator);
+        assert_eq!(defang(&defanged), defanged);
+    }
+}
+
+#[test]
+fn parse_refangs() {
+    let expected = Query::Domain(String::from("evil.com"));
+    assert_eq!(Query::parse("evil[.]com").unwrap(), expected);
+    assert_eq!(Query::parse_as("hxxps://evil[.]com/x", QueryKind::Domain).unwrap(), expected);
+    assert_eq!(
+        Query::parse_as("user[at]evil(dot)com", QueryKind::Email).unwrap(),
+        Query::Email(String::from("user@evil.com"))
+    );
+    assert_eq!(Query::parse("1.2.3[.]4").unwrap().to_string(), "1.2.3.4");
+}
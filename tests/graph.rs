@@ -0,0 +1,163 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use passivetotal_reqwest::{EdgeKind, Expansion, Graph, Pivot, PivotEdge, PivotNode, Query};
+
+const GRAPHML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="kind" for="node" attr.name="kind" attr.type="string"/>
+  <key id="value" for="node" attr.name="value" attr.type="string"/>
+  <key id="name" for="node" attr.name="name" attr.type="string"/>
+  <key id="relation" for="edge" attr.name="relation" attr.type="string"/>
+  <graph id="passivetotal" edgedefault="directed">
+    <node id="asn:AS64500">
+      <data key="kind">asn</data>
+      <data key="value">AS64500</data>
+      <data key="name">Acme &amp; &quot;Net&quot; \ Inc</data>
+    </node>
+    <node id="domain:evil.com">
+      <data key="kind">domain</data>
+      <data key="value">evil.com</data>
+    </node>
+    <node id="ip:1.2.3.4">
+      <data key="kind">ip</data>
+      <data key="value">1.2.3.4</data>
+    </node>
+    <edge source="domain:evil.com" target="ip:1.2.3.4">
+      <data key="relation">resolves-to</data>
+    </edge>
+    <edge source="ip:1.2.3.4" target="asn:AS64500">
+      <data key="relation">announced-by</data>
+    </edge>
+  </graph>
+</graphml>
+"#;
+
+const DOT: &str = r#"digraph passivetotal {
+  "asn:AS64500" [label="AS64500", kind="asn", shape=hexagon, "name"="Acme & \"Net\" \\ Inc"];
+  "domain:evil.com" [label="evil.com", kind="domain", shape=ellipse];
+  "ip:1.2.3.4" [label="1.2.3.4", kind="ip", shape=box];
+  "domain:evil.com" -> "ip:1.2.3.4" [label="resolves-to"];
+  "ip:1.2.3.4" -> "asn:AS64500" [label="announced-by"];
+}
+"#;
+
+fn q(s: &str) -> Query {
+    Query::parse(s).unwrap()
+}
+
+// evil.com resolves to 1.2.3.4, which is announced by an AS whose name has
+// characters that need escaping
+fn small_graph() -> Graph {
+    let mut graph = Graph::new();
+    graph.add_passive_dns(&q("evil.com"), &json!({"results": [{"resolve": "1.2.3.4"}]}));
+    graph.add_enrichment(
+        &q("1.2.3.4"),
+        &json!({
+            "autonomousSystemNumber": 64500,
+            "autonomousSystemName": "Acme & \"Net\" \\ Inc",
+        }),
+    );
+    graph
+}
+
+#[test]
+fn graphml() {
+    let mut out = Vec::new();
+    small_graph().write_graphml(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), GRAPHML);
+}
+
+#[test]
+fn dot() {
+    let mut out = Vec::new();
+    small_graph().write_dot(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), DOT);
+}
+
+#[test]
+fn json_graph() {
+    let expected = json!({
+        "graph": {
+            "directed": true,
+            "nodes": {
+                "asn:AS64500": {
+                    "label": "AS64500",
+                    "metadata": {"kind": "asn", "name": "Acme & \"Net\" \\ Inc"},
+                },
+                "domain:evil.com": {"label": "evil.com", "metadata": {"kind": "domain"}},
+                "ip:1.2.3.4": {"label": "1.2.3.4", "metadata": {"kind": "ip"}},
+            },
+            "edges": [
+                {
+                    "source": "domain:evil.com",
+                    "target": "ip:1.2.3.4",
+                    "relation": "resolves-to",
+                    "directed": true,
+                },
+                {
+                    "source": "ip:1.2.3.4",
+                    "target": "asn:AS64500",
+                    "relation": "announced-by",
+                    "directed": true,
+                },
+            ],
+        }
+    });
+    assert_eq!(small_graph().to_json_graph(), expected);
+}
+
+#[test]
+fn expansion_edge_direction() {
+    let edge = |from: &str, to: &str, pivot| PivotEdge {
+        from: q(from),
+        to: q(to),
+        pivot,
+    };
+    let node = |query: &str| PivotNode {
+        query: q(query),
+        depth: 0,
+        pivot: None,
+        from: None,
+    };
+    let cert = "a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2";
+    let expansion = Expansion {
+        nodes: vec![node("evil.com")],
+        edges: vec![
+            edge("evil.com", "1.2.3.4", Pivot::PassiveDns),
+            edge("5.6.7.8", "other.com", Pivot::PassiveDns),
+            edge("evil.com", "owner@evil.com", Pivot::WhoisEmail),
+            edge("owner@evil.com", "third.com", Pivot::WhoisDomains),
+            edge("1.2.3.4", cert, Pivot::SslCertificates),
+            edge(cert, "9.9.9.9", Pivot::SslIps),
+            edge("evil.com", "www.evil.com", Pivot::Subdomains),
+        ],
+        errors: Vec::new(),
+        truncated: false,
+    };
+
+    let graph = Graph::from_expansion(&expansion);
+    let mut edges: Vec<_> = graph
+        .edges()
+        .into_iter()
+        .map(|e| (e.source.as_str(), e.target.as_str(), e.kind))
+        .collect();
+    edges.sort();
+    let cert_id = format!("certificate:{}", cert);
+
+    let mut expected = vec![
+        ("domain:evil.com", "ip:1.2.3.4", EdgeKind::ResolvesTo),
+        ("domain:other.com", "ip:5.6.7.8", EdgeKind::ResolvesTo),
+        ("domain:evil.com", "email:owner@evil.com", EdgeKind::RegisteredBy),
+        ("domain:third.com", "email:owner@evil.com", EdgeKind::RegisteredBy),
+        ("ip:1.2.3.4", cert_id.as_str(), EdgeKind::ServesCertificate),
+        ("ip:9.9.9.9", cert_id.as_str(), EdgeKind::ServesCertificate),
+        ("domain:www.evil.com", "domain:evil.com", EdgeKind::SubdomainOf),
+    ];
+    expected.sort();
+    assert_eq!(edges, expected);
+
+    let seed = graph.node("domain:evil.com").unwrap();
+    assert_eq!(seed.attributes.get("depth").map(String::as_str), Some("0"));
+}
@@ -0,0 +1,39 @@
+extern crate passivetotal_reqwest;
+
+use passivetotal_reqwest::{domain_to_unicode, suspicious_labels, Query};
+
+#[test]
+fn latin_not_suspicious() {
+    assert!(suspicious_labels("www.example.com").is_empty());
+    assert!(suspicious_labels("café.fr").is_empty());
+}
+
+#[test]
+fn mixed_script_label() {
+    // Cyrillic `а` in an otherwise Latin label
+    assert_eq!(suspicious_labels("www.p\u{430}ypal.com"), vec!["p\u{430}ypal"]);
+}
+
+#[test]
+fn whole_script_confusable() {
+    // `аррӏе` is written entirely in Cyrillic letters that look Latin
+    let apple = "\u{430}\u{440}\u{440}\u{4cf}\u{435}";
+    assert_eq!(suspicious_labels(&format!("{}.com", apple)), vec![apple]);
+    assert_eq!(suspicious_labels("xn--80ak6aa92e.com"), vec![apple]);
+}
+
+#[test]
+fn non_latin_not_suspicious() {
+    assert!(suspicious_labels("пример.рф").is_empty());
+    assert!(suspicious_labels("αβγ.gr").is_empty());
+    assert!(suspicious_labels("日本語テスト.jp").is_empty());
+    assert!(suspicious_labels("한국.kr").is_empty());
+}
+
+#[test]
+fn unicode_round_trip() {
+    let q = Query::parse("пример.рф").unwrap();
+    assert_eq!(q.to_string(), "xn--e1afmkfd.xn--p1ai");
+    assert_eq!(q.to_unicode(), "пример.рф");
+    assert_eq!(domain_to_unicode("xn--e1afmkfd.xn--p1ai"), "пример.рф");
+}
@@ -0,0 +1,77 @@
+extern crate passivetotal_reqwest;
+extern crate serde_json;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use passivetotal_reqwest::{DiskCache, EndpointFamily, PassiveTotal, PassiveTotalError, Result};
+use serde_json::Value;
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("passivetotal-offline-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+// Passive DNS responses expire as soon as they are stored
+fn expiring_cache(dir: &Path) -> DiskCache {
+    DiskCache::open(dir)
+        .unwrap()
+        .ttl(EndpointFamily::PassiveDns, Some(Duration::from_secs(0)))
+}
+
+fn assert_cache_miss(result: Result<Value>) {
+    match result {
+        Err(PassiveTotalError::OfflineCacheMiss(_)) => {},
+        other => panic!("expected an OfflineCacheMiss, got {:?}", other),
+    }
+}
+
+#[test]
+fn serves_expired_responses() {
+    let dir = cache_dir("expired");
+    let (url, received) = common::serve(|_| (200, String::from(r#"{"results": ["1.2.3.4"]}"#)));
+    let online = PassiveTotal::with_auth("username", "apikey")
+        .with_base_url(url.as_str())
+        .with_disk_cache(expiring_cache(&dir));
+    let response = online.passive_dns("example.com").unwrap().send().unwrap();
+
+    // The response has expired, so it is fetched again online
+    online.passive_dns("example.com").unwrap().send().unwrap();
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    let offline = PassiveTotal::with_auth("username", "apikey")
+        .with_base_url(url.as_str())
+        .with_disk_cache(expiring_cache(&dir))
+        .with_offline(true);
+    assert_eq!(offline.passive_dns("example.com").unwrap().send().unwrap(), response);
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    // Only the cached request is answered
+    assert_cache_miss(offline.passive_dns("example.org").unwrap().send());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_response_is_a_miss() {
+    let dir = cache_dir("missing");
+    let pt = PassiveTotal::with_auth("username", "apikey")
+        .with_disk_cache(DiskCache::open(&dir).unwrap())
+        .with_offline(true);
+
+    assert_cache_miss(pt.passive_dns("example.com").unwrap().send());
+    assert_cache_miss(pt.whois().information("example.com").unwrap().send());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn no_disk_cache_is_a_miss() {
+    let pt = PassiveTotal::with_auth("username", "apikey").with_offline(true);
+    assert_cache_miss(pt.passive_dns("example.com").unwrap().send());
+}
@@ -0,0 +1,146 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use passivetotal_reqwest::{Paged, PassiveTotalError, Request, Result};
+use serde_json::Value;
+
+// A paged endpoint answering from a list of responses, or with the first
+// response every time if `ignore_page` is set. Pages past the end of the
+// list are empty and `None` responses fail.
+struct FakePaged {
+    responses: Vec<Option<Value>>,
+    ignore_page: bool,
+    page: usize,
+}
+
+impl FakePaged {
+    fn new(responses: Vec<Value>) -> FakePaged {
+        FakePaged {
+            responses: responses.into_iter().map(Some).collect(),
+            ignore_page: false,
+            page: 0,
+        }
+    }
+}
+
+impl Request for FakePaged {
+    fn send(&self) -> Result<Value> {
+        let page = if self.ignore_page { 0 } else { self.page };
+        match self.responses.get(page) {
+            Some(&Some(ref resp)) => Ok(resp.clone()),
+            Some(&None) => Err(PassiveTotalError::ItemParseError(String::from("page failed"))),
+            None => Ok(json!({ "results": [] })),
+        }
+    }
+}
+
+impl Paged for FakePaged {
+    fn set_page(&mut self, page: usize) {
+        self.page = page;
+    }
+
+    fn page_items(&self, resp: &Value) -> Vec<Value> {
+        resp["results"].as_array().cloned().unwrap_or_default()
+    }
+}
+
+// A page of `len` items numbered from `start`
+fn page(start: u64, len: u64) -> Value {
+    json!({ "results": (start..start + len).collect::<Vec<_>>() })
+}
+
+fn numbers<I>(items: I) -> Vec<u64>
+where
+    I: Iterator<Item = Result<Value>>,
+{
+    items.map(|item| item.unwrap().as_u64().unwrap()).collect()
+}
+
+#[test]
+fn stops_on_empty_page() {
+    let mut pages = FakePaged::new(vec![page(0, 3), page(3, 3)]).pages();
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(pages.pages_fetched(), 3);
+}
+
+#[test]
+fn stops_on_short_page() {
+    let mut pages = FakePaged::new(vec![page(0, 3), page(3, 1), page(4, 3)]).pages();
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2, 3]);
+    assert_eq!(pages.pages_fetched(), 2);
+}
+
+#[test]
+fn stops_on_repeated_page() {
+    let mut request = FakePaged::new(vec![page(0, 3)]);
+    request.ignore_page = true;
+
+    let mut pages = request.pages();
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2]);
+    assert_eq!(pages.pages_fetched(), 2);
+}
+
+#[test]
+fn stops_at_total_records() {
+    let responses = (0..3)
+        .map(|i| {
+            let mut page = page(i * 3, 3);
+            page["totalRecords"] = json!(6);
+            page
+        })
+        .collect();
+    let mut pages = FakePaged::new(responses).pages();
+
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(pages.pages_fetched(), 2);
+}
+
+#[test]
+fn max_pages() {
+    let responses = (0..10).map(|i| page(i * 3, 3)).collect();
+    let mut pages = FakePaged::new(responses).pages().max_pages(2);
+
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(pages.pages_fetched(), 2);
+}
+
+#[test]
+fn max_items() {
+    let responses = (0..10).map(|i| page(i * 3, 3)).collect();
+    let mut pages = FakePaged::new(responses).pages().max_items(4);
+
+    assert_eq!(numbers(pages.by_ref()), vec![0, 1, 2, 3]);
+    assert_eq!(pages.pages_fetched(), 2);
+
+    // No more pages are requested once the limit is reached
+    assert!(pages.next().is_none());
+    assert_eq!(pages.pages_fetched(), 2);
+}
+
+#[test]
+fn failed_page_ends_iteration() {
+    let mut request = FakePaged::new(vec![page(0, 3), page(3, 3)]);
+    request.responses[1] = None;
+
+    let mut pages = request.pages();
+    for i in 0..3 {
+        assert_eq!(pages.next().unwrap().unwrap(), json!(i));
+    }
+    assert!(pages.next().unwrap().is_err());
+    assert!(pages.next().is_none());
+}
+
+#[test]
+fn parse_items() {
+    let request = FakePaged::new(vec![json!({ "results": [1, "two", 3] })]);
+    let items: Vec<Result<u64>> = request.pages().items().collect();
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].as_ref().ok(), Some(&1));
+    match items[1] {
+        Err(PassiveTotalError::ItemParseError(_)) => {},
+        ref other => panic!("expected an ItemParseError, got {:?}", other),
+    }
+    assert_eq!(items[2].as_ref().ok(), Some(&3));
+}
@@ -0,0 +1,117 @@
+extern crate passivetotal_reqwest;
+
+use passivetotal_reqwest::{domain_parts, registrable_domain};
+
+// Test vectors from the Public Suffix List's test_psl.txt
+fn check(domain: &str, expected: Option<&str>) {
+    assert_eq!(
+        registrable_domain(domain).as_ref().map(String::as_str),
+        expected,
+        "{}",
+        domain
+    );
+}
+
+#[test]
+fn mixed_case() {
+    check("COM", None);
+    check("example.COM", Some("example.com"));
+    check("WwW.example.COM", Some("example.com"));
+}
+
+#[test]
+fn unlisted_tld() {
+    check("example", None);
+    check("example.example", Some("example.example"));
+    check("b.example.example", Some("example.example"));
+    check("a.b.example.example", Some("example.example"));
+}
+
+#[test]
+fn tld_with_one_rule() {
+    check("biz", None);
+    check("domain.biz", Some("domain.biz"));
+    check("b.domain.biz", Some("domain.biz"));
+    check("a.b.domain.biz", Some("domain.biz"));
+}
+
+#[test]
+fn tld_with_some_two_level_rules() {
+    check("com", None);
+    check("example.com", Some("example.com"));
+    check("b.example.com", Some("example.com"));
+    check("a.b.example.com", Some("example.com"));
+    check("uk", None);
+    check("co.uk", None);
+    check("example.co.uk", Some("example.co.uk"));
+    check("www.example.co.uk", Some("example.co.uk"));
+}
+
+#[test]
+fn tld_with_wildcard_and_exception_rules() {
+    check("jp", None);
+    check("test.jp", Some("test.jp"));
+    check("www.test.jp", Some("test.jp"));
+    check("ac.jp", None);
+    check("test.ac.jp", Some("test.ac.jp"));
+    check("www.test.ac.jp", Some("test.ac.jp"));
+    check("kyoto.jp", None);
+    check("test.kyoto.jp", Some("test.kyoto.jp"));
+    check("ide.kyoto.jp", None);
+    check("b.ide.kyoto.jp", Some("b.ide.kyoto.jp"));
+    check("a.b.ide.kyoto.jp", Some("b.ide.kyoto.jp"));
+    check("c.kobe.jp", None);
+    check("b.c.kobe.jp", Some("b.c.kobe.jp"));
+    check("a.b.c.kobe.jp", Some("b.c.kobe.jp"));
+    check("city.kobe.jp", Some("city.kobe.jp"));
+    check("www.city.kobe.jp", Some("city.kobe.jp"));
+}
+
+#[test]
+fn tld_with_only_wildcard_and_exception() {
+    check("ck", None);
+    check("test.ck", None);
+    check("b.test.ck", Some("b.test.ck"));
+    check("a.b.test.ck", Some("b.test.ck"));
+    check("www.ck", Some("www.ck"));
+    check("www.www.ck", Some("www.ck"));
+}
+
+#[test]
+fn us_k12() {
+    check("us", None);
+    check("test.us", Some("test.us"));
+    check("www.test.us", Some("test.us"));
+    check("ak.us", None);
+    check("test.ak.us", Some("test.ak.us"));
+    check("www.test.ak.us", Some("test.ak.us"));
+    check("k12.ak.us", None);
+    check("test.k12.ak.us", Some("test.k12.ak.us"));
+    check("www.test.k12.ak.us", Some("test.k12.ak.us"));
+}
+
+#[test]
+fn idn_rules() {
+    check("食狮.公司.cn", Some("xn--85x722f.xn--55qx5d.cn"));
+    check("www.食狮.公司.cn", Some("xn--85x722f.xn--55qx5d.cn"));
+    check("shishi.公司.cn", Some("shishi.xn--55qx5d.cn"));
+    check("公司.cn", None);
+    check("xn--85x722f.xn--55qx5d.cn", Some("xn--85x722f.xn--55qx5d.cn"));
+    check("www.xn--85x722f.xn--55qx5d.cn", Some("xn--85x722f.xn--55qx5d.cn"));
+    check("xn--55qx5d.cn", None);
+}
+
+#[test]
+fn parts() {
+    let parts = domain_parts("a.b.example.co.uk").unwrap();
+    assert_eq!(parts.registrable, Some(String::from("example.co.uk")));
+    assert_eq!(parts.suffix, "co.uk");
+    assert_eq!(parts.subdomains, vec!["a", "b"]);
+
+    let parts = domain_parts("www.ck").unwrap();
+    assert_eq!(parts.suffix, "ck");
+    assert!(parts.subdomains.is_empty());
+
+    assert!(domain_parts("1.2.3.4").is_err());
+    assert!(domain_parts(".example.com").is_err());
+}
@@ -0,0 +1,76 @@
+extern crate passivetotal_reqwest;
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use passivetotal_reqwest::{Query, QueryKind};
+
+fn ipv6(s: &str) -> Query {
+    Query::Ipv6(s.parse::<Ipv6Addr>().unwrap())
+}
+
+#[test]
+fn ipv6_bare() {
+    assert_eq!(Query::parse("2001:db8::1").unwrap(), ipv6("2001:db8::1"));
+}
+
+#[test]
+fn ipv6_compressed() {
+    let q = Query::parse("2001:0DB8:0000:0000:0000:0000:0000:0001").unwrap();
+    assert_eq!(q.to_string(), "2001:db8::1");
+}
+
+#[test]
+fn ipv6_brackets() {
+    assert_eq!(Query::parse("[2001:db8::1]").unwrap(), ipv6("2001:db8::1"));
+}
+
+#[test]
+fn ipv6_url() {
+    let q = Query::parse("https://[2001:db8::1]:8443/path?a=b").unwrap();
+    assert_eq!(q, ipv6("2001:db8::1"));
+    assert_eq!(q.to_string(), "2001:db8::1");
+}
+
+#[test]
+fn ipv6_parse_as() {
+    let q = Query::parse_as("2001:db8::1", QueryKind::Ipv6).unwrap();
+    assert_eq!(q.kind(), QueryKind::Ipv6);
+    assert!(Query::parse_as("2001:db8::1", QueryKind::Ipv4).is_err());
+    assert!(Query::parse_as("2001:db8::1", QueryKind::Domain).is_err());
+}
+
+#[test]
+fn ipv4_mapped_mixed() {
+    let expected = Query::Ipv4(Ipv4Addr::new(192, 0, 2, 1));
+    assert_eq!(Query::parse("::ffff:192.0.2.1").unwrap(), expected);
+    assert_eq!(Query::parse("::FFFF:192.0.2.1").unwrap(), expected);
+    assert_eq!(Query::parse("[::ffff:192.0.2.1]").unwrap(), expected);
+    assert_eq!(Query::parse("http://[::ffff:192.0.2.1]/").unwrap(), expected);
+}
+
+#[test]
+fn ipv4_mapped_hex() {
+    let expected = Query::Ipv4(Ipv4Addr::new(192, 0, 2, 1));
+    assert_eq!(Query::parse("::ffff:c000:201").unwrap(), expected);
+    assert_eq!(Query::parse("0:0:0:0:0:ffff:c000:0201").unwrap(), expected);
+}
+
+#[test]
+fn ipv4_mapped_not_ipv6() {
+    assert!(Query::parse_as("::ffff:192.0.2.1", QueryKind::Ipv6).is_err());
+    assert!(Query::parse_as("::ffff:192.0.2.1", QueryKind::Ipv4).is_ok());
+}
+
+#[test]
+fn ipv6_mixed_not_mapped() {
+    let q = Query::parse("64:ff9b::192.0.2.1").unwrap();
+    assert_eq!(q, ipv6("64:ff9b::c000:201"));
+    assert_eq!(q.to_string(), "64:ff9b::c000:201");
+}
+
+#[test]
+fn ipv6_invalid() {
+    assert!(Query::parse("2001:db8::1::2").is_err());
+    assert!(Query::parse("[2001:db8::1").is_err());
+    assert!(Query::parse("http://[2001:db8::g]/").is_err());
+}
@@ -0,0 +1,21 @@
+extern crate passivetotal_reqwest;
+
+use passivetotal_reqwest::{PassiveTotal, Secret};
+
+const APIKEY: &str = "0123456789abcdef";
+
+#[test]
+fn secret_is_redacted() {
+    let secret = Secret::new(APIKEY);
+    assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+    assert_eq!(format!("{}", secret), "[REDACTED]");
+    assert_eq!(secret.expose(), APIKEY);
+}
+
+#[test]
+fn client_debug_hides_apikey() {
+    let pt = PassiveTotal::with_auth("username", APIKEY);
+    let debug = format!("{:?}", pt);
+    assert!(debug.contains("username"));
+    assert!(!debug.contains(APIKEY));
+}
@@ -0,0 +1,122 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use passivetotal_reqwest::{PassiveTotal, TeamstreamEvent, TeamstreamFollower};
+use serde_json::Value;
+
+type Events = Arc<Mutex<Vec<Value>>>;
+
+fn event(focus: &str, dt: &str) -> Value {
+    json!({
+        "username": "analyst@example.com",
+        "source": "web",
+        "type": "search",
+        "focus": focus,
+        "dt": dt,
+        "additional": {},
+    })
+}
+
+// A client for a local API whose teamstream is the returned list of events
+fn local_api(events: Vec<Value>) -> (PassiveTotal, Events) {
+    let events = Arc::new(Mutex::new(events));
+    let shared = events.clone();
+    let (url, _) = common::serve(move |_| {
+        let events = shared.lock().unwrap().clone();
+        (200, json!({ "teamstream": events }).to_string())
+    });
+    let pt = PassiveTotal::with_auth("username", "apikey").with_base_url(url);
+    (pt, events)
+}
+
+fn cursor_file(name: &str) -> PathBuf {
+    let name = format!("passivetotal-cursor-{}-{}.json", name, std::process::id());
+    let path = env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// The datetime saved in a cursor file
+fn saved_datetime(path: &Path) -> Value {
+    let cursor: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+    cursor["datetime"].clone()
+}
+
+fn focuses(events: &[TeamstreamEvent]) -> Vec<String> {
+    events.iter().map(|e| e.focus.clone().unwrap()).collect()
+}
+
+#[test]
+fn cursor_is_saved_and_reloaded() {
+    let path = cursor_file("reload");
+    let (pt, _) = local_api(vec![
+        event("example.org", "2018-01-01 00:00:01"),
+        event("example.com", "2018-01-01 00:00:00"),
+    ]);
+
+    let mut follower = TeamstreamFollower::new(&pt).with_cursor_file(&path).unwrap();
+    assert_eq!(focuses(&follower.poll().unwrap()), vec!["example.com", "example.org"]);
+    assert_eq!(saved_datetime(&path), json!("2018-01-01 00:00:01"));
+
+    // A new follower carries on from the saved cursor and does not return
+    // the same events again
+    let mut follower = TeamstreamFollower::new(&pt).with_cursor_file(&path).unwrap();
+    assert_eq!(follower.cursor(), "2018-01-01T00:00:01Z".parse().ok());
+    assert!(follower.poll().unwrap().is_empty());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn seen_events_are_skipped() {
+    let (pt, events) = local_api(vec![event("example.com", "2018-01-01 00:00:00")]);
+    let mut follower = TeamstreamFollower::new(&pt);
+    assert_eq!(follower.poll().unwrap().len(), 1);
+
+    // The teamstream returns the event at the cursor again, along with a new
+    // event at the same time
+    events
+        .lock()
+        .unwrap()
+        .push(event("example.org", "2018-01-01 00:00:00"));
+    assert_eq!(focuses(&follower.poll().unwrap()), vec!["example.org"]);
+    assert!(follower.poll().unwrap().is_empty());
+}
+
+#[test]
+fn cursor_written_once_per_batch() {
+    let path = cursor_file("batch");
+    let (pt, events) = local_api(vec![
+        event("example.com", "2018-01-01 00:00:00"),
+        event("example.org", "2018-01-01 00:00:01"),
+    ]);
+    let mut follower = TeamstreamFollower::new(&pt)
+        .interval(Duration::from_millis(10))
+        .with_cursor_file(&path)
+        .unwrap();
+
+    // The cursor is only saved once the last event of the batch is returned
+    assert!(follower.next().unwrap().is_ok());
+    assert!(!path.exists());
+    assert!(follower.next().unwrap().is_ok());
+    assert_eq!(saved_datetime(&path), json!("2018-01-01 00:00:01"));
+
+    events
+        .lock()
+        .unwrap()
+        .push(event("example.net", "2018-01-01 00:00:02"));
+    let event = follower.next().unwrap().unwrap();
+    assert_eq!(event.focus, Some(String::from("example.net")));
+    assert_eq!(saved_datetime(&path), json!("2018-01-01 00:00:02"));
+
+    fs::remove_file(&path).unwrap();
+}
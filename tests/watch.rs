@@ -0,0 +1,191 @@
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use passivetotal_reqwest::{
+    EventSink, PassiveTotal, PassiveTotalError, Query, Result, WatchChange, WatchEvent, Watcher,
+};
+use serde_json::Value;
+
+type Responses = Arc<Mutex<HashMap<&'static str, Value>>>;
+
+// Records the events it receives, failing while `failing` is set
+#[derive(Default)]
+struct TestSink {
+    failing: bool,
+    received: Vec<WatchEvent>,
+}
+
+impl EventSink for TestSink {
+    fn emit(&mut self, event: &WatchEvent) -> Result<()> {
+        if self.failing {
+            return Err(PassiveTotalError::WatchError(String::from("sink is down")));
+        }
+        self.received.push(event.clone());
+        Ok(())
+    }
+}
+
+// Every request fails offline without a disk cache, so each check of the
+// domain reports an error for passive DNS, enrichment and WHOIS
+fn offline() -> PassiveTotal {
+    PassiveTotal::with_auth("username", "apikey").with_offline(true)
+}
+
+#[test]
+fn undelivered_events_are_kept() {
+    let pt = offline();
+    let path = env::temp_dir().join(format!("passivetotal-watch-{}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let queries = vec![Query::parse("example.com").unwrap()];
+    let mut watcher = Watcher::new(&pt, queries).with_state_file(&path).unwrap();
+    let mut sink = TestSink {
+        failing: true,
+        ..TestSink::default()
+    };
+
+    let first = watcher.poll().unwrap();
+    assert_eq!(first.len(), 3);
+    assert!(first.iter().all(|e| match e.change {
+        WatchChange::Error { .. } => e.query == "example.com",
+        _ => false,
+    }));
+    assert!(watcher.deliver(&mut sink).is_err());
+    assert!(!path.exists());
+
+    // The failed events come back first, followed by the new ones
+    let second = watcher.poll().unwrap();
+    assert_eq!(second.len(), 6);
+    assert_eq!(&second[..3], &first[..]);
+
+    sink.failing = false;
+    watcher.deliver(&mut sink).unwrap();
+    assert_eq!(sink.received, second);
+    assert!(path.exists());
+
+    // Delivered events are not returned again
+    assert_eq!(watcher.poll().unwrap().len(), 3);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn partly_delivered_events() {
+    let pt = offline();
+    let mut watcher = Watcher::new(&pt, vec![Query::parse("example.com").unwrap()]);
+
+    let events = watcher.poll().unwrap();
+    watcher.delivered(2).unwrap();
+    let remaining = watcher.poll().unwrap();
+    assert_eq!(remaining.len(), 4);
+    assert_eq!(remaining[0], events[2]);
+}
+
+// A client for a local API answering each endpoint with the response set
+// for it in the returned map
+fn local_api() -> (PassiveTotal, Responses) {
+    let responses: Responses = Arc::new(Mutex::new(HashMap::new()));
+    {
+        let mut r = responses.lock().unwrap();
+        r.insert("/dns/passive", resolutions(&["1.2.3.4"]));
+        r.insert("/enrichment", json!({ "classification": "", "tags": ["hosting"] }));
+        r.insert("/whois", json!({ "domain": "example.com", "registrar": "Registrar" }));
+    }
+
+    let shared = responses.clone();
+    let (url, _) = common::serve(move |request| {
+        match shared.lock().unwrap().get(request.path.as_str()) {
+            Some(resp) => (200, resp.to_string()),
+            None => (404, String::from("{}")),
+        }
+    });
+    let pt = PassiveTotal::with_auth("username", "apikey").with_base_url(url);
+    (pt, responses)
+}
+
+fn resolutions(ips: &[&str]) -> Value {
+    let results: Vec<Value> = ips
+        .iter()
+        .map(|ip| json!({ "resolve": ip, "recordType": "A" }))
+        .collect();
+    json!({ "results": results })
+}
+
+// Check the watchlist and mark the events found as delivered
+fn check(watcher: &mut Watcher) -> Vec<WatchChange> {
+    let events = watcher.poll().unwrap();
+    watcher.delivered(events.len()).unwrap();
+    events.into_iter().map(|e| e.change).collect()
+}
+
+#[test]
+fn detects_changes() {
+    let (pt, responses) = local_api();
+    let mut watcher = Watcher::new(&pt, vec![Query::parse("example.com").unwrap()]);
+
+    // The first check records the baseline
+    assert!(check(&mut watcher).is_empty());
+    assert!(check(&mut watcher).is_empty());
+
+    {
+        let mut r = responses.lock().unwrap();
+        r.insert("/dns/passive", resolutions(&["1.2.3.4", "5.6.7.8"]));
+        r.insert(
+            "/enrichment",
+            json!({ "classification": "malicious", "tags": ["hosting", "c2"] }),
+        );
+    }
+    assert_eq!(
+        check(&mut watcher),
+        vec![
+            WatchChange::NewResolution {
+                resolve: String::from("5.6.7.8"),
+                record_type: String::from("A"),
+            },
+            WatchChange::ClassificationChanged {
+                old: None,
+                new: Some(String::from("malicious")),
+            },
+            WatchChange::NewTag {
+                tag: String::from("c2"),
+            },
+        ]
+    );
+
+    responses
+        .lock()
+        .unwrap()
+        .insert("/whois", json!({ "domain": "example.com", "registrar": "Other" }));
+    let changes = check(&mut watcher);
+    assert_eq!(changes.len(), 1);
+    match changes[0] {
+        WatchChange::WhoisChanged { ref changes } => assert_eq!(changes[0].field, "registrar"),
+        ref other => panic!("expected a WHOIS change, got {:?}", other),
+    }
+}
+
+#[test]
+fn flapping_values_reported_once() {
+    let (pt, responses) = local_api();
+    let mut watcher = Watcher::new(&pt, vec![Query::parse("example.com").unwrap()]);
+    assert!(check(&mut watcher).is_empty());
+
+    let set = |ips: &[&str]| {
+        responses.lock().unwrap().insert("/dns/passive", resolutions(ips));
+    };
+    set(&["5.6.7.8"]);
+    assert_eq!(check(&mut watcher).len(), 1);
+
+    // Resolutions that drop out of the results and come back are not new
+    set(&["1.2.3.4"]);
+    assert!(check(&mut watcher).is_empty());
+    set(&["1.2.3.4", "5.6.7.8"]);
+    assert!(check(&mut watcher).is_empty());
+}
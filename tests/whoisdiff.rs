@@ -0,0 +1,111 @@
+extern crate chrono;
+extern crate passivetotal_reqwest;
+#[macro_use]
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+
+use chrono::Utc;
+use passivetotal_reqwest::{diff_whois, ChangeKind, WhoisStore};
+use serde_json::Value;
+
+fn record() -> Value {
+    json!({
+        "domain": "example.com",
+        "registrar": "Registrar Inc.",
+        "nameServers": ["ns1.example.com", "ns2.example.com"],
+        "registrant": {
+            "email": "owner@example.com",
+            "name": "Owner",
+        },
+        "lastLoadedAt": "2018-01-01",
+        "rawText": "Domain Name: EXAMPLE.COM",
+    })
+}
+
+#[test]
+fn unchanged_records() {
+    assert!(diff_whois(&record(), &record()).is_empty());
+
+    // Fields that change on every lookup are ignored
+    let mut new = record();
+    new["lastLoadedAt"] = json!("2018-06-01");
+    new["rawText"] = json!("Domain Name: example.com");
+    assert!(diff_whois(&record(), &new).is_empty());
+
+    // Lists are compared ignoring order and case
+    new["nameServers"] = json!(["NS2.example.com", "ns1.example.com "]);
+    assert!(diff_whois(&record(), &new).is_empty());
+
+    // Empty values are the same as missing ones
+    new["organization"] = json!("");
+    new["registrant"]["telephone"] = json!(null);
+    assert!(diff_whois(&record(), &new).is_empty());
+}
+
+#[test]
+fn changed_fields() {
+    let mut new = record();
+    new["registrar"] = json!("Other Registrar");
+    new["nameServers"] = json!(["ns1.example.net"]);
+    new["registrant"]["email"] = json!("new@example.com");
+    new["registrant"].as_object_mut().unwrap().remove("name");
+    new["organization"] = json!("Example Corp");
+
+    let changes = diff_whois(&record(), &new);
+    let fields: Vec<_> = changes
+        .iter()
+        .map(|c| (c.field.as_str(), c.change))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("nameServers", ChangeKind::Changed),
+            ("organization", ChangeKind::Added),
+            ("registrant.email", ChangeKind::Changed),
+            ("registrant.name", ChangeKind::Removed),
+            ("registrar", ChangeKind::Changed),
+        ]
+    );
+
+    assert_eq!(changes[1].old, None);
+    assert_eq!(changes[1].new, Some(json!("Example Corp")));
+    assert_eq!(changes[2].old, Some(json!("owner@example.com")));
+    assert_eq!(changes[2].new, Some(json!("new@example.com")));
+    assert_eq!(changes[3].old, Some(json!("Owner")));
+    assert_eq!(changes[3].new, None);
+}
+
+#[test]
+fn responses_without_records() {
+    let changes = diff_whois(&json!(null), &json!({"registrar": "Registrar Inc."}));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].change, ChangeKind::Added);
+
+    assert!(diff_whois(&json!([]), &json!({})).is_empty());
+}
+
+#[test]
+fn store_snapshots() {
+    let dir = env::temp_dir().join(format!("passivetotal-whois-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let store = WhoisStore::open(&dir).unwrap();
+    let start = Utc::now();
+
+    assert!(store.add("example.com", &record()).unwrap().is_empty());
+    assert!(store.add("EXAMPLE.com", &record()).unwrap().is_empty());
+    assert_eq!(store.snapshots("example.com").unwrap().len(), 1);
+
+    let mut new = record();
+    new["registrar"] = json!("Other Registrar");
+    assert_eq!(store.add("example.com", &new).unwrap().len(), 1);
+
+    let latest = store.latest("example.com").unwrap().unwrap();
+    assert_eq!(latest.whois, new);
+    // Stored dates are kept to the second
+    assert!(latest.fetched.timestamp() >= start.timestamp());
+    assert!(latest.fetched <= Utc::now());
+
+    fs::remove_dir_all(&dir).unwrap();
+}